@@ -0,0 +1,1311 @@
+use std::rc::Rc;
+
+use crate::bytecode::chunk::{Chunk, OpCode};
+use crate::bytecode::object::{Function, Obj, ObjRef};
+use crate::bytecode::value::Value;
+use crate::bytecode::vm::VM;
+use crate::error::ParseError;
+use crate::scanner::{Scanner, TokenStream};
+use crate::symbol::Symbol;
+use crate::token::{Token, TokenLiteral, TokenType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    None,
+    Assignment, // =
+    Or,         // or
+    And,        // and
+    Equality,   // == !=
+    Comparison, // < > <= >=
+    Term,       // + -
+    Factor,     // * /
+    Unary,      // ! -
+    Call,       // . ()
+    Primary,
+}
+
+impl Precedence {
+    fn next(self) -> Precedence {
+        match self {
+            Precedence::None => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Call,
+            Precedence::Call | Precedence::Primary => Precedence::Primary,
+        }
+    }
+}
+
+type ParseFn<'src, 'r> = fn(&mut Compiler<'src, 'r>, bool);
+
+struct ParseRule<'src, 'r> {
+    prefix: Option<ParseFn<'src, 'r>>,
+    infix: Option<ParseFn<'src, 'r>>,
+    precedence: Precedence,
+}
+
+const MAX_LOCALS: usize = u8::MAX as usize + 1;
+const MAX_UPVALUES: usize = u8::MAX as usize + 1;
+const MAX_ARGUMENTS: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    Script,
+    Function,
+    Initializer,
+    Method,
+}
+
+struct Local {
+    name: Symbol,
+    // `None` while the variable's initializer is being compiled.
+    depth: Option<usize>,
+    // Whether a closure refers to it, so it must be moved off the stack
+    // when its scope ends.
+    is_captured: bool,
+}
+
+struct UpvalueRef {
+    index: u8,
+    is_local: bool,
+}
+
+/// The part of the compiler's state that belongs to one function body.
+/// Function declarations nest, so the compiler keeps a stack of these.
+struct FunctionState {
+    function_type: FunctionType,
+    name: Option<ObjRef>,
+    arity: usize,
+    chunk: Chunk,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueRef>,
+    scope_depth: usize,
+}
+
+impl FunctionState {
+    fn new(function_type: FunctionType, name: Option<ObjRef>) -> Self {
+        // Slot zero holds the callee, which methods see as `this`.
+        let receiver = match function_type {
+            FunctionType::Method | FunctionType::Initializer => Symbol::THIS,
+            FunctionType::Script | FunctionType::Function => Symbol::intern(""),
+        };
+
+        FunctionState {
+            function_type,
+            name,
+            arity: 0,
+            chunk: Chunk::new(),
+            locals: vec![Local { name: receiver, depth: Some(0), is_captured: false }],
+            upvalues: vec![],
+            scope_depth: 0,
+        }
+    }
+}
+
+struct ClassState {
+    has_superclass: bool,
+}
+
+/// Compiles a token stream straight to bytecode, without building a tree.
+/// Expressions are parsed with a Pratt parser driven by `rule`, and local
+/// variables are resolved to stack slots as they are declared.
+pub struct Compiler<'src, 'r> {
+    tokens: TokenStream<'src, 'r>,
+    vm: &'r mut VM,
+    repl: bool,
+    functions: Vec<FunctionState>,
+    classes: Vec<ClassState>,
+    had_error: bool,
+    // Set after an error until the next statement boundary, so that one
+    // mistake does not produce a cascade of follow-up errors.
+    panic_mode: bool,
+}
+
+impl<'src, 'r> Compiler<'src, 'r> {
+    /// A compiler whose objects are allocated on the heap of `vm`, which is
+    /// the VM that must run the result.
+    pub fn new(scanner: Scanner<'src, 'r>, vm: &'r mut VM) -> Self {
+        Compiler {
+            tokens: TokenStream::new(scanner),
+            vm,
+            repl: false,
+            functions: vec![FunctionState::new(FunctionType::Script, None)],
+            classes: vec![],
+            had_error: false,
+            panic_mode: false,
+        }
+    }
+
+    /// A compiler for interactive input, where a trailing expression without
+    /// a semicolon is printed instead of being rejected.
+    pub fn repl(scanner: Scanner<'src, 'r>, vm: &'r mut VM) -> Self {
+        Self {
+            repl: true,
+            ..Self::new(scanner, vm)
+        }
+    }
+
+    /// Compiles the whole program to a function for `VM::interpret`,
+    /// reporting every error found along the way. Returns `None` if there
+    /// were any.
+    pub fn compile(mut self) -> Option<ObjRef> {
+        while !self.check(TokenType::Eof) {
+            self.declaration();
+        }
+        let (function, _) = self.end_function();
+
+        if self.had_error || self.tokens.had_error() {
+            None
+        } else {
+            Some(function)
+        }
+    }
+
+    fn declaration(&mut self) {
+        if self.match_token(TokenType::Class) {
+            self.class_declaration();
+        } else if self.match_token(TokenType::Fun) {
+            self.fun_declaration();
+        } else if self.match_token(TokenType::Var) {
+            self.var_declaration();
+        } else {
+            self.statement();
+        }
+
+        if self.panic_mode {
+            self.synchronize();
+        }
+    }
+
+    fn class_declaration(&mut self) {
+        self.consume(TokenType::Identifier, "Expect class name.");
+        let class_name = self.previous().clone();
+        let name_constant = self.identifier_constant(class_name.symbol());
+        self.declare_variable();
+
+        self.emit_op(OpCode::Class);
+        self.emit_byte(name_constant);
+        self.define_variable(name_constant);
+
+        self.classes.push(ClassState { has_superclass: false });
+
+        if self.match_token(TokenType::Less) {
+            self.consume(TokenType::Identifier, "Expect superclass name.");
+            self.variable(false);
+
+            let superclass_name = self.previous().clone();
+            if superclass_name.symbol() == class_name.symbol() {
+                self.error_at(&superclass_name, "A class can't inherit from itself.");
+            }
+
+            // Methods find the superclass through a local named `super`, in
+            // a scope of its own so each subclass gets its own slot.
+            self.begin_scope();
+            self.add_local(Symbol::SUPER);
+            self.define_variable(0);
+
+            self.named_variable(class_name.symbol(), false);
+            self.emit_op(OpCode::Inherit);
+            if let Some(class) = self.classes.last_mut() {
+                class.has_superclass = true;
+            }
+        }
+
+        // Keep the class on the stack while its methods are attached.
+        self.named_variable(class_name.symbol(), false);
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.");
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
+            self.method();
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.");
+        self.emit_op(OpCode::Pop);
+
+        if self.classes.pop().is_some_and(|class| class.has_superclass) {
+            self.end_scope();
+        }
+    }
+
+    fn method(&mut self) {
+        self.consume(TokenType::Identifier, "Expect method name.");
+        let name = self.previous().symbol();
+        let constant = self.identifier_constant(name);
+
+        let function_type = if name == Symbol::INIT {
+            FunctionType::Initializer
+        } else {
+            FunctionType::Method
+        };
+        self.function(function_type);
+
+        self.emit_op(OpCode::Method);
+        self.emit_byte(constant);
+    }
+
+    fn fun_declaration(&mut self) {
+        let global = self.parse_variable("Expect function name.");
+        // A function may refer to itself, so its name is usable right away.
+        self.mark_initialized();
+        self.function(FunctionType::Function);
+        self.define_variable(global);
+    }
+
+    /// Compiles a function's parameters and body, whose name was just
+    /// consumed, and emits the code that creates a closure over it.
+    fn function(&mut self, function_type: FunctionType) {
+        let kind = if function_type == FunctionType::Function { "function" } else { "method" };
+        let name = self.previous().symbol();
+        let name = self.intern(name.as_str());
+
+        self.functions.push(FunctionState::new(function_type, Some(name)));
+        self.begin_scope();
+
+        self.consume(TokenType::LeftParen, &format!("Expect '(' after {kind} name."));
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if self.state().arity == MAX_ARGUMENTS {
+                    let token = self.peek().clone();
+                    self.error_at(&token, "Can't have more than 255 parameters.");
+                }
+                self.state_mut().arity += 1;
+
+                let constant = self.parse_variable("Expect parameter name.");
+                self.define_variable(constant);
+
+                if !self.match_token(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.");
+        self.consume(TokenType::LeftBrace, &format!("Expect '{{' before {kind} body."));
+        self.block();
+
+        // No `end_scope`: the frame's slots are discarded wholesale on return.
+        let (function, upvalues) = self.end_function();
+        let constant = self.make_constant(Value::Obj(function));
+        self.emit_op(OpCode::Closure);
+        self.emit_byte(constant);
+        for upvalue in upvalues {
+            self.emit_byte(upvalue.is_local as u8);
+            self.emit_byte(upvalue.index);
+        }
+    }
+
+    /// Finishes the innermost function and moves it onto the heap. Returns
+    /// it with the variables it captures from the enclosing function.
+    fn end_function(&mut self) -> (ObjRef, Vec<UpvalueRef>) {
+        self.emit_return();
+        let state = self.functions.pop().expect("function state");
+
+        let function = Function {
+            arity: state.arity,
+            upvalue_count: state.upvalues.len(),
+            chunk: Rc::new(state.chunk),
+            name: state.name,
+        };
+        let roots = self.roots();
+        let function = self.vm.alloc_with_roots(Obj::Function(function), &roots);
+        (function, state.upvalues)
+    }
+
+    fn var_declaration(&mut self) {
+        let global = self.parse_variable("Expect variable name.");
+
+        if self.match_token(TokenType::Equal) {
+            self.expression();
+        } else {
+            self.emit_op(OpCode::Nil);
+        }
+        self.consume(TokenType::Semicolon, "Expect ';' after variable declaration.");
+
+        self.define_variable(global);
+    }
+
+    fn statement(&mut self) {
+        if self.match_token(TokenType::Print) {
+            self.print_statement();
+        } else if self.match_token(TokenType::For) {
+            self.for_statement();
+        } else if self.match_token(TokenType::If) {
+            self.if_statement();
+        } else if self.match_token(TokenType::Return) {
+            self.return_statement();
+        } else if self.match_token(TokenType::While) {
+            self.while_statement();
+        } else if self.match_token(TokenType::LeftBrace) {
+            self.begin_scope();
+            self.block();
+            self.end_scope();
+        } else {
+            self.expression_statement();
+        }
+    }
+
+    fn print_statement(&mut self) {
+        self.expression();
+        self.consume(TokenType::Semicolon, "Expect ';' after value.");
+        self.emit_op(OpCode::Print);
+    }
+
+    fn for_statement(&mut self) {
+        // The initializer's variable is scoped to the loop.
+        self.begin_scope();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.");
+        if self.match_token(TokenType::Semicolon) {
+            // No initializer.
+        } else if self.match_token(TokenType::Var) {
+            self.var_declaration();
+        } else {
+            self.expression_statement();
+        }
+
+        let mut loop_start = self.chunk().code.len();
+        let mut exit_jump = None;
+        if !self.match_token(TokenType::Semicolon) {
+            self.expression();
+            self.consume(TokenType::Semicolon, "Expect ';' after loop condition.");
+
+            exit_jump = Some(self.emit_jump(OpCode::JumpIfFalse));
+            self.emit_op(OpCode::Pop);
+        }
+
+        if !self.match_token(TokenType::RightParen) {
+            // The increment is compiled before the body but runs after it,
+            // so the body jumps back to it and it jumps back to the condition.
+            let body_jump = self.emit_jump(OpCode::Jump);
+            let increment_start = self.chunk().code.len();
+            self.expression();
+            self.emit_op(OpCode::Pop);
+            self.consume(TokenType::RightParen, "Expect ')' after for clauses.");
+
+            self.emit_loop(loop_start);
+            loop_start = increment_start;
+            self.patch_jump(body_jump);
+        }
+
+        self.statement();
+        self.emit_loop(loop_start);
+
+        if let Some(exit_jump) = exit_jump {
+            self.patch_jump(exit_jump);
+            self.emit_op(OpCode::Pop);
+        }
+        self.end_scope();
+    }
+
+    fn if_statement(&mut self) {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.");
+        self.expression();
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.");
+
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+        self.statement();
+
+        let else_jump = self.emit_jump(OpCode::Jump);
+        self.patch_jump(then_jump);
+        self.emit_op(OpCode::Pop);
+
+        // Eagerly taking the `else` binds it to the nearest `if`.
+        if self.match_token(TokenType::Else) {
+            self.statement();
+        }
+        self.patch_jump(else_jump);
+    }
+
+    fn return_statement(&mut self) {
+        let keyword = self.previous().clone();
+        if self.state().function_type == FunctionType::Script {
+            self.error_at(&keyword, "Can't return from top-level code.");
+        }
+
+        if self.match_token(TokenType::Semicolon) {
+            self.emit_return();
+            return;
+        }
+
+        if self.state().function_type == FunctionType::Initializer {
+            self.error_at(&keyword, "Can't return a value from an initializer.");
+        }
+        self.expression();
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.");
+        self.emit_op(OpCode::Return);
+    }
+
+    fn while_statement(&mut self) {
+        let loop_start = self.chunk().code.len();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.");
+        self.expression();
+        self.consume(TokenType::RightParen, "Expect ')' after condition.");
+
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+        self.statement();
+        self.emit_loop(loop_start);
+
+        self.patch_jump(exit_jump);
+        self.emit_op(OpCode::Pop);
+    }
+
+    fn block(&mut self) {
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
+            self.declaration();
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.");
+    }
+
+    fn expression_statement(&mut self) {
+        self.expression();
+
+        if self.repl && self.check(TokenType::Eof) {
+            self.emit_op(OpCode::Print);
+            return;
+        }
+
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.");
+        self.emit_op(OpCode::Pop);
+    }
+
+    fn expression(&mut self) {
+        self.parse_precedence(Precedence::Assignment);
+    }
+
+    fn parse_precedence(&mut self, precedence: Precedence) {
+        // Look before advancing: `advance` stops at the end of input, so an
+        // operator there would otherwise be its own operand forever.
+        let Some(prefix) = Self::rule(self.peek().token_type).prefix else {
+            let token = self.peek().clone();
+            self.error_at(&token, "Expect expression.");
+            return;
+        };
+        self.advance();
+
+        // Only a prefix parsed at the lowest precedence may be the target of
+        // an `=`; otherwise `a + b = c` would assign to `b`.
+        let can_assign = precedence <= Precedence::Assignment;
+        prefix(self, can_assign);
+
+        while precedence <= Self::rule(self.peek().token_type).precedence {
+            self.advance();
+            let infix = Self::rule(self.previous().token_type).infix.expect("infix rule");
+            infix(self, can_assign);
+        }
+
+        if can_assign && self.match_token(TokenType::Equal) {
+            let equals = self.previous().clone();
+            self.error_at(&equals, "Invalid assignment target.");
+        }
+    }
+
+    fn rule(token_type: TokenType) -> ParseRule<'src, 'r> {
+        let (prefix, infix, precedence): (Option<ParseFn<'src, 'r>>, Option<ParseFn<'src, 'r>>, Precedence) = match token_type {
+            TokenType::LeftParen => (Some(Self::grouping), Some(Self::call), Precedence::Call),
+            TokenType::Dot => (None, Some(Self::dot), Precedence::Call),
+            TokenType::Minus => (Some(Self::unary), Some(Self::binary), Precedence::Term),
+            TokenType::Plus => (None, Some(Self::binary), Precedence::Term),
+            TokenType::Slash | TokenType::Star => (None, Some(Self::binary), Precedence::Factor),
+            TokenType::Bang => (Some(Self::unary), None, Precedence::None),
+            TokenType::BangEqual | TokenType::EqualEqual => (None, Some(Self::binary), Precedence::Equality),
+            TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => {
+                (None, Some(Self::binary), Precedence::Comparison)
+            }
+            TokenType::Identifier => (Some(Self::variable), None, Precedence::None),
+            TokenType::String | TokenType::Number | TokenType::False | TokenType::True | TokenType::Nil => {
+                (Some(Self::literal), None, Precedence::None)
+            }
+            TokenType::Interpolation => (Some(Self::interpolation), None, Precedence::None),
+            TokenType::And => (None, Some(Self::and), Precedence::And),
+            TokenType::Or => (None, Some(Self::or), Precedence::Or),
+            TokenType::Super => (Some(Self::super_), None, Precedence::None),
+            TokenType::This => (Some(Self::this), None, Precedence::None),
+            _ => (None, None, Precedence::None),
+        };
+        ParseRule { prefix, infix, precedence }
+    }
+
+    fn grouping(&mut self, _can_assign: bool) {
+        self.expression();
+        self.consume(TokenType::RightParen, "Expect ')' after expression.");
+    }
+
+    fn call(&mut self, _can_assign: bool) {
+        let arg_count = self.argument_list();
+        self.emit_op(OpCode::Call);
+        self.emit_byte(arg_count);
+    }
+
+    fn argument_list(&mut self) -> u8 {
+        let mut arg_count = 0;
+        if !self.check(TokenType::RightParen) {
+            loop {
+                self.expression();
+                if arg_count == MAX_ARGUMENTS {
+                    let token = self.peek().clone();
+                    self.error_at(&token, "Can't have more than 255 arguments.");
+                }
+                arg_count += 1;
+
+                if !self.match_token(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after arguments.");
+        arg_count.min(MAX_ARGUMENTS) as u8
+    }
+
+    fn dot(&mut self, can_assign: bool) {
+        self.consume(TokenType::Identifier, "Expect property name after '.'.");
+        let name = self.previous().symbol();
+        let name = self.identifier_constant(name);
+
+        if can_assign && self.match_token(TokenType::Equal) {
+            self.expression();
+            self.emit_op(OpCode::SetProperty);
+            self.emit_byte(name);
+        } else if self.match_token(TokenType::LeftParen) {
+            let arg_count = self.argument_list();
+            self.emit_op(OpCode::Invoke);
+            self.emit_byte(name);
+            self.emit_byte(arg_count);
+        } else {
+            self.emit_op(OpCode::GetProperty);
+            self.emit_byte(name);
+        }
+    }
+
+    fn unary(&mut self, _can_assign: bool) {
+        let operator = self.previous().token_type;
+        self.parse_precedence(Precedence::Unary);
+
+        match operator {
+            TokenType::Bang => self.emit_op(OpCode::Not),
+            TokenType::Minus => self.emit_op(OpCode::Negate),
+            _ => unreachable!("not a unary operator: {operator}"),
+        }
+    }
+
+    fn binary(&mut self, _can_assign: bool) {
+        let operator = self.previous().token_type;
+        self.parse_precedence(Self::rule(operator).precedence.next());
+
+        match operator {
+            TokenType::BangEqual => {
+                self.emit_op(OpCode::Equal);
+                self.emit_op(OpCode::Not);
+            }
+            TokenType::EqualEqual => self.emit_op(OpCode::Equal),
+            TokenType::Greater => self.emit_op(OpCode::Greater),
+            TokenType::GreaterEqual => self.emit_op(OpCode::GreaterEqual),
+            TokenType::Less => self.emit_op(OpCode::Less),
+            TokenType::LessEqual => self.emit_op(OpCode::LessEqual),
+            TokenType::Plus => self.emit_op(OpCode::Add),
+            TokenType::Minus => self.emit_op(OpCode::Subtract),
+            TokenType::Star => self.emit_op(OpCode::Multiply),
+            TokenType::Slash => self.emit_op(OpCode::Divide),
+            _ => unreachable!("not a binary operator: {operator}"),
+        }
+    }
+
+    /// `and` short-circuits by jumping over its right operand, leaving the
+    /// falsey left operand as the result.
+    fn and(&mut self, _can_assign: bool) {
+        let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+
+        self.emit_op(OpCode::Pop);
+        self.parse_precedence(Precedence::And);
+
+        self.patch_jump(end_jump);
+    }
+
+    fn or(&mut self, _can_assign: bool) {
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+        let end_jump = self.emit_jump(OpCode::Jump);
+
+        self.patch_jump(else_jump);
+        self.emit_op(OpCode::Pop);
+        self.parse_precedence(Precedence::Or);
+
+        self.patch_jump(end_jump);
+    }
+
+    fn literal(&mut self, _can_assign: bool) {
+        match self.previous().token_type {
+            TokenType::False => self.emit_op(OpCode::False),
+            TokenType::True => self.emit_op(OpCode::True),
+            TokenType::Nil => self.emit_op(OpCode::Nil),
+            _ => match self.previous().literal {
+                Some(TokenLiteral::Number(n)) => self.emit_constant(Value::Number(n)),
+                Some(TokenLiteral::String(text)) => {
+                    let string = self.intern(text.as_str());
+                    self.emit_constant(Value::Obj(string));
+                }
+                literal => unreachable!("not a literal: {literal:?}"),
+            },
+        }
+    }
+
+    /// Compiles an interpolated string: each piece of text and each embedded
+    /// expression is pushed in turn, then joined by one instruction.
+    fn interpolation(&mut self, _can_assign: bool) {
+        let mut part_count = 0;
+        loop {
+            part_count += self.segment();
+            self.expression();
+            part_count += 1;
+
+            // After a broken expression, or at the end of input, there is no
+            // next segment to find.
+            if self.panic_mode || self.check(TokenType::Eof) || !self.match_token(TokenType::Interpolation) {
+                break;
+            }
+        }
+
+        self.consume(TokenType::InterpolationEnd, "Expect '}' after interpolated expression.");
+        part_count += self.segment();
+
+        if part_count > MAX_ARGUMENTS {
+            let token = self.previous().clone();
+            self.error_at(&token, "Too many parts in string interpolation.");
+        }
+        self.emit_op(OpCode::Interpolate);
+        self.emit_byte(part_count.min(MAX_ARGUMENTS) as u8);
+    }
+
+    /// Pushes the text of the string segment just consumed, unless it is
+    /// empty. Returns how many parts were pushed.
+    fn segment(&mut self) -> usize {
+        match self.previous().literal {
+            Some(TokenLiteral::String(text)) if !text.as_str().is_empty() => {
+                let string = self.intern(text.as_str());
+                self.emit_constant(Value::Obj(string));
+                1
+            }
+            _ => 0,
+        }
+    }
+
+    fn variable(&mut self, can_assign: bool) {
+        let name = self.previous().symbol();
+        self.named_variable(name, can_assign);
+    }
+
+    fn this(&mut self, _can_assign: bool) {
+        if self.classes.is_empty() {
+            let keyword = self.previous().clone();
+            self.error_at(&keyword, "Can't use 'this' outside of a class.");
+            return;
+        }
+
+        // `this` can't be assigned to.
+        self.variable(false);
+    }
+
+    fn super_(&mut self, _can_assign: bool) {
+        let keyword = self.previous().clone();
+        match self.classes.last() {
+            None => self.error_at(&keyword, "Can't use 'super' outside of a class."),
+            Some(class) if !class.has_superclass => {
+                self.error_at(&keyword, "Can't use 'super' in a class with no superclass.")
+            }
+            Some(_) => {}
+        }
+
+        self.consume(TokenType::Dot, "Expect '.' after 'super'.");
+        self.consume(TokenType::Identifier, "Expect superclass method name.");
+        let name = self.previous().symbol();
+        let name = self.identifier_constant(name);
+
+        self.named_variable(Symbol::THIS, false);
+        if self.match_token(TokenType::LeftParen) {
+            let arg_count = self.argument_list();
+            self.named_variable(Symbol::SUPER, false);
+            self.emit_op(OpCode::SuperInvoke);
+            self.emit_byte(name);
+            self.emit_byte(arg_count);
+        } else {
+            self.named_variable(Symbol::SUPER, false);
+            self.emit_op(OpCode::GetSuper);
+            self.emit_byte(name);
+        }
+    }
+
+    fn named_variable(&mut self, name: Symbol, can_assign: bool) {
+        let innermost = self.functions.len() - 1;
+        let (get_op, set_op, operand) = if let Some(slot) = self.resolve_local(innermost, name) {
+            (OpCode::GetLocal, OpCode::SetLocal, slot)
+        } else if let Some(index) = self.resolve_upvalue(innermost, name) {
+            (OpCode::GetUpvalue, OpCode::SetUpvalue, index)
+        } else {
+            (OpCode::GetGlobal, OpCode::SetGlobal, self.identifier_constant(name))
+        };
+
+        if can_assign && self.match_token(TokenType::Equal) {
+            self.expression();
+            self.emit_op(set_op);
+        } else {
+            self.emit_op(get_op);
+        }
+        self.emit_byte(operand);
+    }
+
+    /// Consumes a variable name and declares it. Returns the constant holding
+    /// the name for a global, which is only looked up by name at runtime.
+    fn parse_variable(&mut self, message: &str) -> u8 {
+        self.consume(TokenType::Identifier, message);
+
+        self.declare_variable();
+        if self.state().scope_depth > 0 {
+            return 0;
+        }
+
+        let name = self.previous().symbol();
+        self.identifier_constant(name)
+    }
+
+    fn define_variable(&mut self, global: u8) {
+        if self.state().scope_depth > 0 {
+            // The value is already in the local's stack slot.
+            self.mark_initialized();
+            return;
+        }
+
+        self.emit_op(OpCode::DefineGlobal);
+        self.emit_byte(global);
+    }
+
+    fn mark_initialized(&mut self) {
+        let state = self.state_mut();
+        if state.scope_depth == 0 {
+            return;
+        }
+        if let Some(local) = state.locals.last_mut() {
+            local.depth = Some(state.scope_depth);
+        }
+    }
+
+    /// Declares the variable named by the previous token as a local of the
+    /// current scope. Globals are late bound and need no declaration.
+    fn declare_variable(&mut self) {
+        let state = self.state();
+        if state.scope_depth == 0 {
+            return;
+        }
+
+        let name = self.previous().clone();
+        let redeclared = state.locals.iter().rev()
+            .take_while(|local| local.depth.is_none_or(|depth| depth >= state.scope_depth))
+            .any(|local| local.name == name.symbol());
+        if redeclared {
+            self.error_at(&name, "Already a variable with this name in this scope.");
+        }
+
+        self.add_local(name.symbol());
+    }
+
+    fn add_local(&mut self, name: Symbol) {
+        if self.state().locals.len() == MAX_LOCALS {
+            let token = self.previous().clone();
+            self.error_at(&token, "Too many local variables in function.");
+            return;
+        }
+        self.state_mut().locals.push(Local { name, depth: None, is_captured: false });
+    }
+
+    fn resolve_local(&mut self, function: usize, name: Symbol) -> Option<u8> {
+        let (slot, local) = self.functions[function].locals.iter().enumerate().rev()
+            .find(|(_, local)| local.name == name)?;
+
+        if local.depth.is_none() {
+            let token = self.previous().clone();
+            self.error_at(&token, "Can't read local variable in its own initializer.");
+        }
+        Some(slot as u8)
+    }
+
+    /// Finds `name` in the functions enclosing `function`, threading it
+    /// through each one in between as an upvalue.
+    fn resolve_upvalue(&mut self, function: usize, name: Symbol) -> Option<u8> {
+        let enclosing = function.checked_sub(1)?;
+
+        if let Some(slot) = self.resolve_local(enclosing, name) {
+            self.functions[enclosing].locals[slot as usize].is_captured = true;
+            return Some(self.add_upvalue(function, slot, true));
+        }
+
+        let index = self.resolve_upvalue(enclosing, name)?;
+        Some(self.add_upvalue(function, index, false))
+    }
+
+    fn add_upvalue(&mut self, function: usize, index: u8, is_local: bool) -> u8 {
+        let upvalues = &self.functions[function].upvalues;
+        if let Some(existing) = upvalues.iter().position(|upvalue| upvalue.index == index && upvalue.is_local == is_local) {
+            return existing as u8;
+        }
+
+        if upvalues.len() == MAX_UPVALUES {
+            let token = self.previous().clone();
+            self.error_at(&token, "Too many closure variables in function.");
+            return 0;
+        }
+
+        let upvalues = &mut self.functions[function].upvalues;
+        upvalues.push(UpvalueRef { index, is_local });
+        (upvalues.len() - 1) as u8
+    }
+
+    fn identifier_constant(&mut self, name: Symbol) -> u8 {
+        let string = self.intern(name.as_str());
+        self.make_constant(Value::Obj(string))
+    }
+
+    fn begin_scope(&mut self) {
+        self.state_mut().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.state_mut().scope_depth -= 1;
+
+        loop {
+            let state = self.state();
+            let Some(local) = state.locals.last() else {
+                break;
+            };
+            if local.depth.is_some_and(|depth| depth <= state.scope_depth) {
+                break;
+            }
+
+            let op = if local.is_captured { OpCode::CloseUpvalue } else { OpCode::Pop };
+            self.state_mut().locals.pop();
+            self.emit_op(op);
+        }
+    }
+
+    fn state(&self) -> &FunctionState {
+        self.functions.last().expect("function state")
+    }
+
+    fn state_mut(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().expect("function state")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state_mut().chunk
+    }
+
+    /// Every object the functions still being compiled refer to. They are
+    /// not on the heap yet, so a collection would not otherwise see them.
+    fn roots(&self) -> Vec<ObjRef> {
+        self.functions.iter()
+            .flat_map(|state| state.name.into_iter().chain(state.chunk.constants.iter().filter_map(Value::as_obj)))
+            .collect()
+    }
+
+    fn intern(&mut self, text: &str) -> ObjRef {
+        let roots = self.roots();
+        self.vm.intern_with_roots(text, &roots)
+    }
+
+    fn emit_byte(&mut self, byte: u8) {
+        let line = self.previous().line() as u32;
+        self.chunk().write(byte, line);
+    }
+
+    fn emit_op(&mut self, op: OpCode) {
+        self.emit_byte(op as u8);
+    }
+
+    fn emit_return(&mut self) {
+        if self.state().function_type == FunctionType::Initializer {
+            self.emit_op(OpCode::GetLocal);
+            self.emit_byte(0);
+        } else {
+            self.emit_op(OpCode::Nil);
+        }
+        self.emit_op(OpCode::Return);
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        let constant = self.make_constant(value);
+        self.emit_op(OpCode::Constant);
+        self.emit_byte(constant);
+    }
+
+    fn make_constant(&mut self, value: Value) -> u8 {
+        let constant = self.chunk().add_constant(value);
+        u8::try_from(constant).unwrap_or_else(|_| {
+            let token = self.previous().clone();
+            self.error_at(&token, "Too many constants in one chunk.");
+            0
+        })
+    }
+
+    /// Emits a jump with a placeholder offset, returning where the offset is
+    /// so `patch_jump` can fill it in once the target is known.
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit_op(op);
+        self.emit_byte(0xff);
+        self.emit_byte(0xff);
+        self.chunk().code.len() - 2
+    }
+
+    fn patch_jump(&mut self, offset: usize) {
+        // -2 to skip over the jump offset itself.
+        let jump = self.chunk().code.len() - offset - 2;
+        let Ok(jump) = u16::try_from(jump) else {
+            let token = self.previous().clone();
+            self.error_at(&token, "Too much code to jump over.");
+            return;
+        };
+
+        self.chunk().code[offset..offset + 2].copy_from_slice(&jump.to_be_bytes());
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) {
+        self.emit_op(OpCode::Loop);
+
+        // +2 to also jump back over the loop instruction's own offset.
+        let offset = self.chunk().code.len() - loop_start + 2;
+        let offset = u16::try_from(offset).unwrap_or_else(|_| {
+            let token = self.previous().clone();
+            self.error_at(&token, "Loop body too large.");
+            0
+        });
+        for byte in offset.to_be_bytes() {
+            self.emit_byte(byte);
+        }
+    }
+
+    fn error_at(&mut self, token: &Token<'src>, message: &str) {
+        if self.panic_mode {
+            return;
+        }
+        self.panic_mode = true;
+        self.had_error = true;
+        self.tokens.reporter().report(ParseError::new(token, message).to_diagnostic());
+    }
+
+    /// Discards tokens until the start of the next statement.
+    fn synchronize(&mut self) {
+        self.panic_mode = false;
+
+        while !self.check(TokenType::Eof) {
+            if self.previous().token_type == TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => {}
+            }
+
+            self.advance();
+        }
+    }
+
+    fn consume(&mut self, token_type: TokenType, message: &str) {
+        if self.check(token_type) {
+            self.advance();
+            return;
+        }
+
+        let token = self.peek().clone();
+        self.error_at(&token, message);
+    }
+
+    fn match_token(&mut self, token_type: TokenType) -> bool {
+        if !self.check(token_type) {
+            return false;
+        }
+        self.advance();
+        true
+    }
+
+    fn check(&self, token_type: TokenType) -> bool {
+        self.peek().token_type == token_type
+    }
+
+    fn advance(&mut self) {
+        self.tokens.advance();
+    }
+
+    fn previous(&self) -> &Token<'src> {
+        self.tokens.previous()
+    }
+
+    fn peek(&self) -> &Token<'src> {
+        self.tokens.peek()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytecode::debug::disassemble_chunk;
+    use crate::bytecode::vm::{VmError, VM};
+    use crate::diagnostic::Diagnostic;
+    use crate::parser::Parse;
+    use crate::reporter::Collector;
+    use crate::scanner::Scanner;
+    use crate::test_support::Output;
+
+    use super::*;
+
+    fn compile(source: &str, vm: &mut VM) -> Result<ObjRef, Vec<Diagnostic>> {
+        let mut collector = Collector::new();
+        let function = Compiler::new(Scanner::new(source, &mut collector), vm).compile();
+        function.ok_or_else(|| collector.take())
+    }
+
+    fn compile_error(source: &str) -> String {
+        let errors = compile(source, &mut VM::new()).unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        errors[0].message.clone()
+    }
+
+    fn disassemble(source: &str) -> String {
+        let mut vm = VM::new();
+        let function = compile(source, &mut vm).unwrap();
+        disassemble_chunk(&vm.heap().function(function).chunk, vm.heap(), "script")
+    }
+
+    fn run(source: &str) -> Result<String, VmError> {
+        let output = Output::default();
+        let mut vm = VM::with_output(Box::new(output.clone()));
+        // Collecting on every allocation shakes out objects the compiler
+        // or the VM forgot to root.
+        vm.set_gc_stress(true);
+        let function = compile(source, &mut vm).unwrap();
+        vm.interpret(function)?;
+        Ok(output.contents())
+    }
+
+    #[test]
+    fn test_compile_expression() {
+        assert_eq!(disassemble("print -(1 + 2) * 3 >= 4;\n!nil;"), "\
+== script ==
+0000    1 OP_CONSTANT         0 '1'
+0002    | OP_CONSTANT         1 '2'
+0004    | OP_ADD
+0005    | OP_NEGATE
+0006    | OP_CONSTANT         2 '3'
+0008    | OP_MULTIPLY
+0009    | OP_CONSTANT         3 '4'
+0011    | OP_GREATER_EQUAL
+0012    | OP_PRINT
+0013    2 OP_NIL
+0014    | OP_NOT
+0015    | OP_POP
+0016    | OP_NIL
+0017    | OP_RETURN
+");
+    }
+
+    #[test]
+    fn test_compile_locals_and_jumps() {
+        assert_eq!(disassemble("{ var a = 1; if (a) a = 2; }"), "\
+== script ==
+0000    1 OP_CONSTANT         0 '1'
+0002    | OP_GET_LOCAL        1
+0004    | OP_JUMP_IF_FALSE    4 -> 16
+0007    | OP_POP
+0008    | OP_CONSTANT         1 '2'
+0010    | OP_SET_LOCAL        1
+0012    | OP_POP
+0013    | OP_JUMP            13 -> 17
+0016    | OP_POP
+0017    | OP_POP
+0018    | OP_NIL
+0019    | OP_RETURN
+");
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        assert_eq!(run(r#"var n = 2; print "${n} + ${n} = ${n + n}!";"#).unwrap(), "2 + 2 = 4!\n");
+        assert_eq!(run(r#"class A {} fun f() {} print "${A()} ${f} ${"<${nil}>"}";"#).unwrap(), "A instance <fn f> <nil>\n");
+        assert_eq!(compile_error(r#"print "${1 2}";"#), "Expect '}' after interpolated expression.");
+    }
+
+    #[test]
+    fn test_globals_and_strings() {
+        assert_eq!(run("var a = \"lo\"; var b = a + \"x\"; a = b; print a; print a == \"lox\";").unwrap(), "lox\ntrue\n");
+        assert_eq!(run("print c;").unwrap_err(), VmError { message: String::from("Undefined variable 'c'."), line: 1 });
+        assert_eq!(run("c = 1;").unwrap_err().message, "Undefined variable 'c'.");
+    }
+
+    #[test]
+    fn test_block_scoping() {
+        let source = "var a = \"global\"; { var a = \"outer\"; { var a = \"inner\"; print a; } print a; } print a;";
+        assert_eq!(run(source).unwrap(), "inner\nouter\nglobal\n");
+    }
+
+    #[test]
+    fn test_control_flow() {
+        assert_eq!(run("if (1 > 2) print 1; else print 2; if (nil) print 3; if (true) print 4;").unwrap(), "2\n4\n");
+        assert_eq!(run("var i = 0; while (i < 3) { print i; i = i + 1; }").unwrap(), "0\n1\n2\n");
+        assert_eq!(run("for (var i = 0; i < 3; i = i + 1) print i; var i = 9; print i;").unwrap(), "0\n1\n2\n9\n");
+        assert_eq!(run("print nil or \"yes\"; print 0 and false; print nil and 1; print 1 or 2;").unwrap(), "yes\nfalse\nnil\n1\n");
+    }
+
+    #[test]
+    fn test_functions_and_closures() {
+        let source = "fun add(a, b) { return a + b; } print add(1, 2); print add;";
+        assert_eq!(run(source).unwrap(), "3\n<fn add>\n");
+
+        let source = "
+            fun counter() {
+                var i = 0;
+                fun count() { i = i + 1; return i; }
+                return count;
+            }
+            var a = counter(); var b = counter();
+            a(); a(); b();
+            print a(); print b();";
+        assert_eq!(run(source).unwrap(), "3\n2\n");
+
+        // Closures made in a loop body each capture their own variable.
+        let source = "
+            var first; var second;
+            for (var i = 1; i < 3; i = i + 1) {
+                var j = i;
+                fun f() { print j; }
+                if (first == nil) first = f; else second = f;
+            }
+            first(); second();";
+        assert_eq!(run(source).unwrap(), "1\n2\n");
+
+        assert_eq!(run("fun f(a) {} f(1, 2);").unwrap_err().message, "Expected 1 arguments but got 2.");
+        assert_eq!(run("\"f\"();").unwrap_err().message, "Can only call functions and classes.");
+        assert_eq!(run("fun f() { f(); } f();").unwrap_err().message, "Stack overflow.");
+    }
+
+    #[test]
+    fn test_closure_upvalues() {
+        assert_eq!(disassemble("{ var a = 1; fun f() { print a; } }"), "\
+== script ==
+0000    1 OP_CONSTANT         0 '1'
+0002    | OP_CLOSURE          1 <fn f>
+0004    |                     local 1
+0006    | OP_POP
+0007    | OP_CLOSE_UPVALUE
+0008    | OP_NIL
+0009    | OP_RETURN
+");
+    }
+
+    #[test]
+    fn test_classes() {
+        let source = "
+            class Point {
+                init(x, y) { this.x = x; this.y = y; }
+                sum() { return this.x + this.y; }
+            }
+            var p = Point(1, 2);
+            print p.sum();
+            p.x = 10;
+            var sum = p.sum;
+            print sum();
+            print p; print Point;";
+        assert_eq!(run(source).unwrap(), "3\n12\nPoint instance\nPoint\n");
+
+        let source = "
+            class A { greet() { return \"A\"; } name() { return \"a\"; } }
+            class B < A {
+                greet() { return \"B after \" + super.greet(); }
+                name() { var method = super.name; return method(); }
+            }
+            print B().greet(); print B().name();";
+        assert_eq!(run(source).unwrap(), "B after A\na\n");
+
+        assert_eq!(run("class A { init() { return; } } print A().init();").unwrap(), "A instance\n");
+        assert_eq!(run("class A {} A().missing;").unwrap_err().message, "Undefined property 'missing'.");
+        assert_eq!(run("var a = 1; a.x = 2;").unwrap_err().message, "Only instances have fields.");
+        assert_eq!(run("var a = 1; class B < a {}").unwrap_err().message, "Superclass must be a class.");
+    }
+
+    #[test]
+    fn test_errors_match_tree_walker() {
+        let errors = compile("print (1 + 2;\n1 +;\nprint 1", &mut VM::new()).unwrap_err();
+
+        let messages = errors.iter().map(|error| error.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, vec!["Expect ')' after expression.", "Expect expression.", "Expect ';' after value."]);
+        assert_eq!(errors[2].primary.message, "at end of input");
+
+        assert_eq!(compile_error("1 + a = 3;"), "Invalid assignment target.");
+        assert_eq!(compile_error("{ var a = 1; var a = 2; }"), "Already a variable with this name in this scope.");
+        assert_eq!(compile_error("{ var a = a; }"), "Can't read local variable in its own initializer.");
+        assert_eq!(compile_error("var = 1;"), "Expect variable name.");
+        assert_eq!(compile_error("while (true print 1;"), "Expect ')' after condition.");
+        assert_eq!(compile_error("return 1;"), "Can't return from top-level code.");
+        assert_eq!(compile_error("class A { init() { return 1; } }"), "Can't return a value from an initializer.");
+        assert_eq!(compile_error("print this;"), "Can't use 'this' outside of a class.");
+        assert_eq!(compile_error("class A < A {}"), "A class can't inherit from itself.");
+        assert_eq!(compile_error("fun f() { super.g(); }"), "Can't use 'super' outside of a class.");
+        assert_eq!(compile_error("class A { f() { super.g(); } }"), "Can't use 'super' in a class with no superclass.");
+        assert_eq!(compile_error("fun (a) {}"), "Expect function name.");
+        assert_eq!(compile_error("class A { f( {} }"), "Expect parameter name.");
+    }
+
+    #[test]
+    fn test_repl_trailing_expression() {
+        let mut collector = Collector::new();
+        let mut vm = VM::new();
+        let function = Compiler::repl(Scanner::new("1 + 2", &mut collector), &mut vm).compile().unwrap();
+
+        let chunk = &vm.heap().function(function).chunk;
+        assert_eq!(chunk.code[chunk.code.len() - 3], OpCode::Print as u8);
+    }
+
+    #[test]
+    fn test_operator_at_end_of_input() {
+        for source in ["print -", "print !"] {
+            let errors = compile(source, &mut VM::new()).unwrap_err();
+
+            assert_eq!(errors.len(), 1, "{source}");
+            assert_eq!(errors[0].message, "Expect expression.");
+            assert_eq!(errors[0].primary.message, "at end of input");
+        }
+    }
+
+    #[test]
+    fn test_interpolation_at_end_of_input() {
+        for source in ["print \"${", "print \"${\";"] {
+            let errors = compile(source, &mut VM::new()).unwrap_err();
+
+            let last = errors.last().unwrap();
+            assert_eq!(last.message, "Expect expression.", "{source}");
+            assert_eq!(last.primary.message, "at end of input", "{source}");
+        }
+    }
+
+    #[test]
+    fn test_missing_expression_matches_tree_walker() {
+        for source in ["var a = 1 +", "print @;", "print (;", "a = ;"] {
+            let mut collector = Collector::new();
+            Parse::new(Scanner::new(source, &mut collector)).parse();
+            let parsed = collector.take();
+
+            let compiled = compile(source, &mut VM::new()).unwrap_err();
+            let expression = |errors: &[Diagnostic]| {
+                errors.iter()
+                    .find(|error| error.message == "Expect expression.")
+                    .map(|error| (error.primary.span, error.primary.message.clone()))
+            };
+            assert!(expression(&parsed).is_some(), "{source}");
+            assert_eq!(expression(&compiled), expression(&parsed), "{source}");
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt::Write;
+
+use crate::bytecode::chunk::{Chunk, OpCode};
+use crate::bytecode::heap::Heap;
+
+/// Renders every instruction in `chunk` in the same layout as clox's
+/// disassembler: byte offset, source line (or `|` when unchanged), opcode
+/// name and operands.
+pub fn disassemble_chunk(chunk: &Chunk, heap: &Heap, name: &str) -> String {
+    let mut out = String::new();
+    writeln!(out, "== {name} ==").unwrap();
+
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        offset = disassemble_instruction(chunk, heap, offset, &mut out);
+    }
+    out
+}
+
+/// Writes the instruction at `offset` to `out` and returns the offset of
+/// the next one.
+pub fn disassemble_instruction(chunk: &Chunk, heap: &Heap, offset: usize, out: &mut String) -> usize {
+    write!(out, "{offset:04} ").unwrap();
+    if offset > 0 && chunk.lines[offset] == chunk.lines[offset - 1] {
+        write!(out, "   | ").unwrap();
+    } else {
+        write!(out, "{:4} ", chunk.lines[offset]).unwrap();
+    }
+
+    let byte = chunk.code[offset];
+    let Ok(op) = OpCode::try_from(byte) else {
+        writeln!(out, "Unknown opcode {byte}").unwrap();
+        return offset + 1;
+    };
+
+    match op {
+        OpCode::Constant
+        | OpCode::GetGlobal
+        | OpCode::DefineGlobal
+        | OpCode::SetGlobal
+        | OpCode::GetProperty
+        | OpCode::SetProperty
+        | OpCode::GetSuper
+        | OpCode::Class
+        | OpCode::Method => constant_instruction(op, chunk, heap, offset, out),
+        OpCode::GetLocal
+        | OpCode::SetLocal
+        | OpCode::GetUpvalue
+        | OpCode::SetUpvalue
+        | OpCode::Call
+        | OpCode::Interpolate => {
+            byte_instruction(op, chunk, offset, out)
+        }
+        OpCode::Invoke | OpCode::SuperInvoke => invoke_instruction(op, chunk, heap, offset, out),
+        OpCode::Closure => closure_instruction(op, chunk, heap, offset, out),
+        OpCode::Jump | OpCode::JumpIfFalse => jump_instruction(op, 1, chunk, offset, out),
+        OpCode::Loop => jump_instruction(op, -1, chunk, offset, out),
+        _ => simple_instruction(op, offset, out),
+    }
+}
+
+fn simple_instruction(op: OpCode, offset: usize, out: &mut String) -> usize {
+    writeln!(out, "{}", op.name()).unwrap();
+    offset + 1
+}
+
+fn constant_instruction(op: OpCode, chunk: &Chunk, heap: &Heap, offset: usize, out: &mut String) -> usize {
+    let constant = chunk.code[offset + 1];
+    writeln!(out, "{:<16} {constant:4} '{}'", op.name(), heap.display(chunk.constants[constant as usize])).unwrap();
+    offset + 2
+}
+
+fn invoke_instruction(op: OpCode, chunk: &Chunk, heap: &Heap, offset: usize, out: &mut String) -> usize {
+    let constant = chunk.code[offset + 1];
+    let arg_count = chunk.code[offset + 2];
+    let name = heap.display(chunk.constants[constant as usize]);
+    writeln!(out, "{:<16} ({arg_count} args) {constant:4} '{name}'", op.name()).unwrap();
+    offset + 3
+}
+
+/// A closure is followed by a pair of bytes for each variable it captures:
+/// whether it is a local of the enclosing function, and its slot or index.
+fn closure_instruction(op: OpCode, chunk: &Chunk, heap: &Heap, offset: usize, out: &mut String) -> usize {
+    let constant = chunk.code[offset + 1];
+    let function = chunk.constants[constant as usize];
+    writeln!(out, "{:<16} {constant:4} {}", op.name(), heap.display(function)).unwrap();
+
+    let upvalue_count = function.as_obj().map_or(0, |function| heap.function(function).upvalue_count);
+    let mut offset = offset + 2;
+    for _ in 0..upvalue_count {
+        let kind = if chunk.code[offset] == 1 { "local" } else { "upvalue" };
+        let index = chunk.code[offset + 1];
+        writeln!(out, "{offset:04}    |                     {kind} {index}").unwrap();
+        offset += 2;
+    }
+    offset
+}
+
+fn byte_instruction(op: OpCode, chunk: &Chunk, offset: usize, out: &mut String) -> usize {
+    let slot = chunk.code[offset + 1];
+    writeln!(out, "{:<16} {slot:4}", op.name()).unwrap();
+    offset + 2
+}
+
+fn jump_instruction(op: OpCode, sign: isize, chunk: &Chunk, offset: usize, out: &mut String) -> usize {
+    let jump = u16::from_be_bytes([chunk.code[offset + 1], chunk.code[offset + 2]]);
+    let target = offset as isize + 3 + sign * jump as isize;
+    writeln!(out, "{:<16} {offset:4} -> {target}", op.name()).unwrap();
+    offset + 3
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytecode::value::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_disassemble_chunk() {
+        let mut chunk = Chunk::new();
+        let constant = chunk.add_constant(Value::Number(1.2));
+        chunk.write_op(OpCode::Constant, 123);
+        chunk.write(constant as u8, 123);
+        chunk.write_op(OpCode::Negate, 123);
+        chunk.write_op(OpCode::Return, 124);
+        chunk.write(255, 124);
+
+        assert_eq!(disassemble_chunk(&chunk, &Heap::new(), "test chunk"), "\
+== test chunk ==
+0000  123 OP_CONSTANT         0 '1.2'
+0002    | OP_NEGATE
+0003  124 OP_RETURN
+0004    | Unknown opcode 255
+");
+    }
+}
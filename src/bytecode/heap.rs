@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::bytecode::object::{BoundMethod, Class, Closure, Function, Instance, Obj, ObjRef, Upvalue};
+use crate::bytecode::value::Value;
+
+/// The heap size that triggers the first collection.
+const INITIAL_NEXT_GC: usize = 1024 * 1024;
+
+/// After a collection, the next one happens once the heap has grown this
+/// many times larger than what survived.
+const GC_HEAP_GROW_FACTOR: usize = 2;
+
+struct Slot {
+    obj: Obj,
+    // The size charged to `bytes_allocated` for this object.
+    size: usize,
+    marked: bool,
+}
+
+/// Owns every object the VM and compiler allocate, and reclaims the ones
+/// that are no longer reachable with a mark-and-sweep collector.
+///
+/// Strings are interned, so two strings are equal exactly when their
+/// handles are. The intern table does not keep strings alive.
+///
+/// The heap doesn't know what is reachable; the VM decides when to collect
+/// (see `should_collect`), marks its roots and then calls `collect`.
+pub struct Heap {
+    slots: Vec<Option<Slot>>,
+    free: Vec<u32>,
+    strings: HashMap<String, ObjRef>,
+    gray: Vec<ObjRef>,
+    bytes_allocated: usize,
+    next_gc: usize,
+    /// Collect before every allocation, to flush out objects that are
+    /// reachable but not rooted.
+    pub stress: bool,
+    /// Print a line to stderr for every collection.
+    pub log: bool,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Heap {
+            slots: vec![],
+            free: vec![],
+            strings: HashMap::new(),
+            gray: vec![],
+            bytes_allocated: 0,
+            next_gc: INITIAL_NEXT_GC,
+            stress: false,
+            log: false,
+        }
+    }
+
+    pub fn bytes_allocated(&self) -> usize {
+        self.bytes_allocated
+    }
+
+    /// The number of objects currently on the heap.
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether allocating now should be preceded by a collection.
+    pub fn should_collect(&self) -> bool {
+        self.stress || self.bytes_allocated > self.next_gc
+    }
+
+    /// Moves `obj` onto the heap. This never collects: callers check
+    /// `should_collect` first, while they can still mark their roots.
+    pub fn alloc(&mut self, obj: Obj) -> ObjRef {
+        let size = obj.size();
+        self.bytes_allocated += size;
+
+        let slot = Some(Slot { obj, size, marked: false });
+        match self.free.pop() {
+            Some(index) => {
+                self.slots[index as usize] = slot;
+                ObjRef(index)
+            }
+            None => {
+                self.slots.push(slot);
+                ObjRef(self.slots.len() as u32 - 1)
+            }
+        }
+    }
+
+    /// Looks up an interned string without allocating.
+    pub fn find_string(&self, text: &str) -> Option<ObjRef> {
+        self.strings.get(text).copied()
+    }
+
+    /// Allocates a string that `find_string` has confirmed is not interned.
+    pub fn alloc_string(&mut self, text: String) -> ObjRef {
+        debug_assert!(!self.strings.contains_key(&text));
+        let string = self.alloc(Obj::String(text.clone()));
+        self.strings.insert(text, string);
+        string
+    }
+
+    pub fn get(&self, obj: ObjRef) -> &Obj {
+        match &self.slots[obj.0 as usize] {
+            Some(slot) => &slot.obj,
+            None => panic!("use of freed object {obj:?}"),
+        }
+    }
+
+    fn get_mut(&mut self, obj: ObjRef) -> &mut Obj {
+        match &mut self.slots[obj.0 as usize] {
+            Some(slot) => &mut slot.obj,
+            None => panic!("use of freed object {obj:?}"),
+        }
+    }
+
+    pub fn string(&self, obj: ObjRef) -> &str {
+        match self.get(obj) {
+            Obj::String(text) => text,
+            obj => unreachable!("expected a string, got {obj:?}"),
+        }
+    }
+
+    pub fn function(&self, obj: ObjRef) -> &Function {
+        match self.get(obj) {
+            Obj::Function(function) => function,
+            obj => unreachable!("expected a function, got {obj:?}"),
+        }
+    }
+
+    pub fn closure(&self, obj: ObjRef) -> &Closure {
+        match self.get(obj) {
+            Obj::Closure(closure) => closure,
+            obj => unreachable!("expected a closure, got {obj:?}"),
+        }
+    }
+
+    pub fn class(&self, obj: ObjRef) -> &Class {
+        match self.get(obj) {
+            Obj::Class(class) => class,
+            obj => unreachable!("expected a class, got {obj:?}"),
+        }
+    }
+
+    pub fn upvalue(&self, obj: ObjRef) -> &Upvalue {
+        match self.get(obj) {
+            Obj::Upvalue(upvalue) => upvalue,
+            obj => unreachable!("expected an upvalue, got {obj:?}"),
+        }
+    }
+
+    pub fn upvalue_mut(&mut self, obj: ObjRef) -> &mut Upvalue {
+        match self.get_mut(obj) {
+            Obj::Upvalue(upvalue) => upvalue,
+            obj => unreachable!("expected an upvalue, got {obj:?}"),
+        }
+    }
+
+    pub fn as_str(&self, value: Value) -> Option<&str> {
+        match self.get(value.as_obj()?) {
+            Obj::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_instance(&self, value: Value) -> Option<&Instance> {
+        match self.get(value.as_obj()?) {
+            Obj::Instance(instance) => Some(instance),
+            _ => None,
+        }
+    }
+
+    /// Sets a field on `instance`, charging any growth of its field table.
+    pub fn set_field(&mut self, instance: ObjRef, name: ObjRef, value: Value) {
+        if let Obj::Instance(instance) = self.get_mut(instance) {
+            instance.fields.insert(name, value);
+        }
+        self.recharge(instance);
+    }
+
+    /// Adds a method to `class`, charging any growth of its method table.
+    pub fn set_method(&mut self, class: ObjRef, name: ObjRef, method: ObjRef) {
+        if let Obj::Class(class) = self.get_mut(class) {
+            class.methods.insert(name, method);
+        }
+        self.recharge(class);
+    }
+
+    /// Copies every method of `superclass` into `subclass`, so method
+    /// lookup never has to walk the inheritance chain.
+    pub fn inherit(&mut self, subclass: ObjRef, superclass: ObjRef) {
+        let methods = self.class(superclass).methods.clone();
+        if let Obj::Class(class) = self.get_mut(subclass) {
+            class.methods.extend(methods);
+        }
+        self.recharge(subclass);
+    }
+
+    fn recharge(&mut self, obj: ObjRef) {
+        if let Some(slot) = &mut self.slots[obj.0 as usize] {
+            let size = slot.obj.size();
+            self.bytes_allocated = self.bytes_allocated - slot.size + size;
+            slot.size = size;
+        }
+    }
+
+    pub fn mark_value(&mut self, value: Value) {
+        if let Value::Obj(obj) = value {
+            self.mark_object(obj);
+        }
+    }
+
+    pub fn mark_object(&mut self, obj: ObjRef) {
+        let Some(slot) = &mut self.slots[obj.0 as usize] else {
+            panic!("marked freed object {obj:?}");
+        };
+        if slot.marked {
+            return;
+        }
+        slot.marked = true;
+        self.gray.push(obj);
+    }
+
+    /// Frees everything not reachable from the objects marked since the last
+    /// collection, and returns the number of bytes freed.
+    pub fn collect(&mut self) -> usize {
+        let before = self.bytes_allocated;
+
+        self.trace_references();
+        self.remove_white_strings();
+        self.sweep();
+        self.next_gc = (self.bytes_allocated * GC_HEAP_GROW_FACTOR).max(INITIAL_NEXT_GC);
+
+        let freed = before - self.bytes_allocated;
+        if self.log {
+            eprintln!(
+                "-- gc: freed {freed} bytes ({before} -> {}), next at {}",
+                self.bytes_allocated, self.next_gc
+            );
+        }
+        freed
+    }
+
+    /// Blackens gray objects until none are left: each one's references are
+    /// marked, turning them gray in turn.
+    fn trace_references(&mut self) {
+        let mut references = vec![];
+        while let Some(obj) = self.gray.pop() {
+            self.get(obj).references(&mut references);
+            for reference in references.drain(..) {
+                self.mark_object(reference);
+            }
+        }
+    }
+
+    fn remove_white_strings(&mut self) {
+        let slots = &self.slots;
+        self.strings.retain(|_, string| {
+            slots[string.0 as usize].as_ref().is_some_and(|slot| slot.marked)
+        });
+    }
+
+    fn sweep(&mut self) {
+        for (index, entry) in self.slots.iter_mut().enumerate() {
+            match entry {
+                Some(slot) if slot.marked => slot.marked = false,
+                Some(slot) => {
+                    self.bytes_allocated -= slot.size;
+                    *entry = None;
+                    self.free.push(index as u32);
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Formats `value` the way `print` shows it.
+    pub fn display(&self, value: Value) -> impl fmt::Display + '_ {
+        Display { heap: self, value }
+    }
+}
+
+struct Display<'h> {
+    heap: &'h Heap,
+    value: Value,
+}
+
+impl fmt::Display for Display<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Value::Obj(obj) = self.value else {
+            return write!(f, "{}", self.value);
+        };
+
+        let heap = self.heap;
+        match heap.get(obj) {
+            Obj::String(text) => write!(f, "{}", text),
+            Obj::Function(function) => match function.name {
+                Some(name) => write!(f, "<fn {}>", heap.string(name)),
+                None => write!(f, "<script>"),
+            },
+            Obj::Native(_) => write!(f, "<native fn>"),
+            Obj::Closure(closure) => write!(f, "{}", heap.display(Value::Obj(closure.function))),
+            Obj::Upvalue(_) => write!(f, "upvalue"),
+            Obj::Class(class) => write!(f, "{}", heap.string(class.name)),
+            Obj::Instance(instance) => write!(f, "{} instance", heap.string(heap.class(instance.class).name)),
+            Obj::BoundMethod(BoundMethod { method, .. }) => write!(f, "{}", heap.display(Value::Obj(*method))),
+        }
+    }
+}
+
+impl fmt::Debug for Heap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Heap")
+            .field("objects", &self.len())
+            .field("bytes_allocated", &self.bytes_allocated)
+            .field("next_gc", &self.next_gc)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(heap: &mut Heap, text: &str) -> ObjRef {
+        heap.find_string(text).unwrap_or_else(|| heap.alloc_string(text.to_string()))
+    }
+
+    #[test]
+    fn test_strings_are_interned() {
+        let mut heap = Heap::new();
+        let a = string(&mut heap, "lox");
+        let b = string(&mut heap, &(String::from("lo") + "x"));
+
+        assert_eq!(a, b);
+        assert_ne!(a, string(&mut heap, "clox"));
+        assert_eq!(heap.as_str(Value::Obj(a)), Some("lox"));
+        assert_eq!(heap.display(Value::Obj(a)).to_string(), "lox");
+        assert_eq!(heap.display(Value::Number(2.5)).to_string(), "2.5");
+    }
+
+    #[test]
+    fn test_collect_frees_unmarked_objects() {
+        let mut heap = Heap::new();
+        let kept = string(&mut heap, "kept");
+        let dropped = string(&mut heap, "dropped");
+        let before = heap.bytes_allocated();
+
+        heap.mark_object(kept);
+        let freed = heap.collect();
+
+        assert_eq!(heap.len(), 1);
+        assert!(freed > 0);
+        assert_eq!(heap.bytes_allocated(), before - freed);
+        assert_eq!(heap.find_string("dropped"), None);
+        assert_eq!(heap.find_string("kept"), Some(kept));
+
+        // The freed slot is reused.
+        assert_eq!(string(&mut heap, "new"), dropped);
+    }
+
+    #[test]
+    fn test_collect_traces_through_cycles() {
+        let mut heap = Heap::new();
+        let name = string(&mut heap, "Node");
+        let class = heap.alloc(Obj::Class(Class { name, methods: HashMap::new() }));
+        let field = string(&mut heap, "next");
+        let instance = heap.alloc(Obj::Instance(Instance { class, fields: HashMap::new() }));
+        heap.set_field(instance, field, Value::Obj(instance));
+
+        heap.mark_object(instance);
+        heap.collect();
+        assert_eq!(heap.len(), 4);
+
+        // Nothing refers to the cycle from outside any more.
+        heap.collect();
+        assert_eq!(heap.len(), 0);
+        assert_eq!(heap.bytes_allocated(), 0);
+    }
+}
@@ -0,0 +1,10 @@
+//! A second execution engine modeled on clox: source is compiled to a flat
+//! `Chunk` of bytecode which a stack-based `VM` then runs.
+
+pub mod chunk;
+pub mod compiler;
+pub mod debug;
+pub mod heap;
+pub mod object;
+pub mod value;
+pub mod vm;
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+
+use crate::bytecode::chunk::Chunk;
+use crate::bytecode::value::Value;
+
+/// A handle to an object on the VM heap. Values hold handles rather than
+/// pointers, which keeps `Value` small and `Copy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjRef(pub(crate) u32);
+
+#[derive(Debug)]
+pub enum Obj {
+    String(String),
+    Function(Function),
+    Native(Native),
+    Closure(Closure),
+    Upvalue(Upvalue),
+    Class(Class),
+    Instance(Instance),
+    BoundMethod(BoundMethod),
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub arity: usize,
+    pub upvalue_count: usize,
+    // Shared so a running call frame can hold on to the code without
+    // borrowing the heap.
+    pub chunk: Rc<Chunk>,
+    // `None` for the top-level script.
+    pub name: Option<ObjRef>,
+}
+
+pub struct Native {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: fn(&[Value]) -> Value,
+}
+
+#[derive(Debug)]
+pub struct Closure {
+    pub function: ObjRef,
+    pub upvalues: Vec<ObjRef>,
+}
+
+/// A variable captured by a closure. It points into the value stack while
+/// the variable's scope is live, and holds the value itself once it closes.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: ObjRef,
+    pub methods: HashMap<ObjRef, ObjRef>,
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: ObjRef,
+    pub fields: HashMap<ObjRef, Value>,
+}
+
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Value,
+    pub method: ObjRef,
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Native({})", self.name)
+    }
+}
+
+impl Obj {
+    /// Appends every object this one keeps alive to `out`.
+    pub fn references(&self, out: &mut Vec<ObjRef>) {
+        match self {
+            Obj::String(_) | Obj::Native(_) | Obj::Upvalue(Upvalue::Open(_)) => {}
+            Obj::Function(function) => {
+                out.extend(function.name);
+                out.extend(function.chunk.constants.iter().filter_map(Value::as_obj));
+            }
+            Obj::Closure(closure) => {
+                out.push(closure.function);
+                out.extend(&closure.upvalues);
+            }
+            Obj::Upvalue(Upvalue::Closed(value)) => out.extend(value.as_obj()),
+            Obj::Class(class) => {
+                out.push(class.name);
+                for (name, method) in class.methods.iter() {
+                    out.extend([*name, *method]);
+                }
+            }
+            Obj::Instance(instance) => {
+                out.push(instance.class);
+                for (name, value) in instance.fields.iter() {
+                    out.push(*name);
+                    out.extend(value.as_obj());
+                }
+            }
+            Obj::BoundMethod(bound) => {
+                out.extend(bound.receiver.as_obj());
+                out.push(bound.method);
+            }
+        }
+    }
+
+    /// An estimate of the memory this object owns, used to decide when to
+    /// collect. It only needs to grow with the real footprint, not match it.
+    pub fn size(&self) -> usize {
+        let owned = match self {
+            Obj::String(text) => text.capacity(),
+            Obj::Function(function) => {
+                let chunk = &function.chunk;
+                chunk.code.capacity() + chunk.lines.capacity() * mem::size_of::<u32>()
+                    + chunk.constants.capacity() * mem::size_of::<Value>()
+            }
+            Obj::Native(_) | Obj::Upvalue(_) | Obj::BoundMethod(_) => 0,
+            Obj::Closure(closure) => closure.upvalues.capacity() * mem::size_of::<ObjRef>(),
+            Obj::Class(class) => class.methods.capacity() * mem::size_of::<(ObjRef, ObjRef)>(),
+            Obj::Instance(instance) => instance.fields.capacity() * mem::size_of::<(ObjRef, Value)>(),
+        };
+        mem::size_of::<Obj>() + owned
+    }
+}
@@ -0,0 +1,789 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::bytecode::chunk::{Chunk, OpCode};
+use crate::bytecode::heap::Heap;
+use crate::bytecode::object::{BoundMethod, Class, Closure, Instance, Native, Obj, ObjRef, Upvalue};
+use crate::bytecode::value::Value;
+
+const FRAMES_MAX: usize = 64;
+const STACK_MAX: usize = FRAMES_MAX * (u8::MAX as usize + 1);
+
+/// A runtime error raised by the VM. It carries the source line of the
+/// failing instruction, since bytecode no longer has tokens to point at.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VmError {
+    pub message: String,
+    pub line: u32,
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n[line {}]", self.message, self.line)
+    }
+}
+
+/// An active function call. `slots` is the index of the stack slot holding
+/// the callee, which is followed by the arguments and then the locals.
+struct CallFrame {
+    closure: ObjRef,
+    chunk: Rc<Chunk>,
+    ip: usize,
+    slots: usize,
+}
+
+pub struct VM {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    globals: HashMap<ObjRef, Value>,
+    // Upvalues still pointing into the stack, ordered by stack slot.
+    open_upvalues: Vec<ObjRef>,
+    init_string: ObjRef,
+    heap: Heap,
+    output: Box<dyn Write>,
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VM {
+    pub fn new() -> Self {
+        Self::with_output(Box::new(io::stdout()))
+    }
+
+    /// A VM whose `print` statements write to `output` instead of stdout.
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        let mut heap = Heap::new();
+        let init_string = heap.alloc_string(String::from("init"));
+
+        let mut vm = VM {
+            stack: Vec::with_capacity(STACK_MAX),
+            frames: Vec::with_capacity(FRAMES_MAX),
+            globals: HashMap::new(),
+            open_upvalues: vec![],
+            init_string,
+            heap,
+            output,
+        };
+        vm.define_native("clock", 0, clock);
+        vm
+    }
+
+    pub fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    /// Collect garbage before every allocation.
+    pub fn set_gc_stress(&mut self, stress: bool) {
+        self.heap.stress = stress;
+    }
+
+    /// Print the number of bytes freed by every collection to stderr.
+    pub fn set_gc_log(&mut self, log: bool) {
+        self.heap.log = log;
+    }
+
+    /// Runs a script compiled by `Compiler`. Globals and heap objects survive
+    /// from one call to the next, as the REPL needs.
+    pub fn interpret(&mut self, function: ObjRef) -> Result<(), VmError> {
+        // The function stays on the stack while the closure is allocated,
+        // so a collection can't free it.
+        self.push(Value::Obj(function));
+        let closure = self.alloc(Obj::Closure(Closure { function, upvalues: vec![] }));
+        self.pop();
+        self.push(Value::Obj(closure));
+
+        let result = self.call_closure(closure, 0)
+            .map_err(|message| VmError { message, line: 0 })
+            .and_then(|()| self.run());
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+            self.open_upvalues.clear();
+        }
+        result
+    }
+
+    fn run(&mut self) -> Result<(), VmError> {
+        let (mut chunk, mut ip, mut slots) = self.load_frame();
+
+        macro_rules! read_byte {
+            () => {{
+                ip += 1;
+                chunk.code[ip - 1]
+            }};
+        }
+        macro_rules! read_short {
+            () => {{
+                ip += 2;
+                u16::from_be_bytes([chunk.code[ip - 2], chunk.code[ip - 1]])
+            }};
+        }
+        macro_rules! read_constant {
+            () => {
+                chunk.constants[read_byte!() as usize]
+            };
+        }
+        macro_rules! read_string {
+            () => {
+                read_constant!().as_obj().expect("name constants are strings")
+            };
+        }
+
+        loop {
+            let line = chunk.lines[ip];
+            let error = |message: String| VmError { message, line };
+
+            let op = OpCode::try_from(read_byte!()).map_err(|byte| error(format!("Unknown opcode {byte}.")))?;
+            match op {
+                OpCode::Constant => {
+                    let constant = read_constant!();
+                    self.push(constant);
+                }
+                OpCode::Nil => self.push(Value::Nil),
+                OpCode::True => self.push(Value::Bool(true)),
+                OpCode::False => self.push(Value::Bool(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal => {
+                    let slot = read_byte!() as usize;
+                    self.push(self.stack[slots + slot]);
+                }
+                OpCode::SetLocal => {
+                    let slot = read_byte!() as usize;
+                    // Assignment is an expression, so the value stays on the stack.
+                    self.stack[slots + slot] = self.peek(0);
+                }
+                OpCode::GetGlobal => {
+                    let name = read_string!();
+                    match self.globals.get(&name) {
+                        Some(&value) => self.push(value),
+                        None => return Err(error(self.undefined_variable(name))),
+                    }
+                }
+                OpCode::DefineGlobal => {
+                    let name = read_string!();
+                    // Read before popping, in case the insert has to grow the table.
+                    self.globals.insert(name, self.peek(0));
+                    self.pop();
+                }
+                OpCode::SetGlobal => {
+                    let name = read_string!();
+                    let value = self.peek(0);
+                    match self.globals.get_mut(&name) {
+                        Some(global) => *global = value,
+                        None => return Err(error(self.undefined_variable(name))),
+                    }
+                }
+                OpCode::GetUpvalue => {
+                    let upvalue = self.frame_upvalue(read_byte!());
+                    let value = match self.heap.upvalue(upvalue) {
+                        Upvalue::Open(slot) => self.stack[*slot],
+                        Upvalue::Closed(value) => *value,
+                    };
+                    self.push(value);
+                }
+                OpCode::SetUpvalue => {
+                    let upvalue = self.frame_upvalue(read_byte!());
+                    let value = self.peek(0);
+                    match self.heap.upvalue_mut(upvalue) {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                }
+                OpCode::GetProperty => {
+                    let name = read_string!();
+                    let Some(instance) = self.heap.as_instance(self.peek(0)) else {
+                        return Err(error(String::from("Only instances have properties.")));
+                    };
+
+                    match instance.fields.get(&name) {
+                        Some(&value) => {
+                            self.pop();
+                            self.push(value);
+                        }
+                        None => self.bind_method(instance.class, name).map_err(error)?,
+                    }
+                }
+                OpCode::SetProperty => {
+                    let name = read_string!();
+                    let instance = match self.peek(1) {
+                        Value::Obj(obj) if matches!(self.heap.get(obj), Obj::Instance(_)) => obj,
+                        _ => return Err(error(String::from("Only instances have fields."))),
+                    };
+
+                    self.heap.set_field(instance, name, self.peek(0));
+                    let value = self.pop();
+                    self.pop();
+                    self.push(value);
+                }
+                OpCode::GetSuper => {
+                    let name = read_string!();
+                    let superclass = self.pop().as_obj().expect("superclass");
+                    self.bind_method(superclass, name).map_err(error)?;
+                }
+                OpCode::Equal => {
+                    let (a, b) = self.pop_pair();
+                    self.push(Value::Bool(a == b));
+                }
+                OpCode::Greater => self.comparison(|a, b| a > b).map_err(error)?,
+                OpCode::GreaterEqual => self.comparison(|a, b| a >= b).map_err(error)?,
+                OpCode::Less => self.comparison(|a, b| a < b).map_err(error)?,
+                OpCode::LessEqual => self.comparison(|a, b| a <= b).map_err(error)?,
+                OpCode::Add => {
+                    let (a, b) = (self.peek(1), self.peek(0));
+                    if let (Value::Number(a), Value::Number(b)) = (a, b) {
+                        self.pop_pair();
+                        self.push(Value::Number(a + b));
+                        continue;
+                    }
+
+                    let (Some(a), Some(b)) = (self.heap.as_str(a), self.heap.as_str(b)) else {
+                        return Err(error(String::from("Operands must be two numbers or two strings.")));
+                    };
+                    let text = format!("{a}{b}");
+                    let string = self.intern(&text);
+                    self.pop_pair();
+                    self.push(Value::Obj(string));
+                }
+                OpCode::Subtract => self.arithmetic(|a, b| a - b).map_err(error)?,
+                OpCode::Multiply => self.arithmetic(|a, b| a * b).map_err(error)?,
+                OpCode::Divide => self.arithmetic(|a, b| a / b).map_err(error)?,
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.push(Value::Bool(value.is_falsey()));
+                }
+                OpCode::Negate => match self.pop() {
+                    Value::Number(n) => self.push(Value::Number(-n)),
+                    _ => return Err(error(String::from("Operand must be a number."))),
+                },
+                OpCode::Print => {
+                    let value = self.pop();
+                    // A closed stdout is not a Lox runtime error, so the write result is ignored.
+                    let _ = writeln!(self.output, "{}", self.heap.display(value));
+                }
+                OpCode::Jump => {
+                    let offset = read_short!();
+                    ip += offset as usize;
+                }
+                OpCode::JumpIfFalse => {
+                    let offset = read_short!();
+                    // The condition is left on the stack; the compiler pops it on both paths.
+                    if self.peek(0).is_falsey() {
+                        ip += offset as usize;
+                    }
+                }
+                OpCode::Loop => {
+                    let offset = read_short!();
+                    ip -= offset as usize;
+                }
+                OpCode::Interpolate => {
+                    // The parts stay on the stack, and so reachable, until
+                    // the result has been interned.
+                    let count = read_byte!() as usize;
+                    let parts = &self.stack[self.stack.len() - count..];
+                    let text: String = parts.iter().map(|part| self.heap.display(*part).to_string()).collect();
+                    let string = self.intern(&text);
+                    self.stack.truncate(self.stack.len() - count);
+                    self.push(Value::Obj(string));
+                }
+                OpCode::Call => {
+                    let arg_count = read_byte!() as usize;
+                    self.save_ip(ip);
+                    self.call_value(self.peek(arg_count), arg_count).map_err(error)?;
+                    (chunk, ip, slots) = self.load_frame();
+                }
+                OpCode::Invoke => {
+                    let name = read_string!();
+                    let arg_count = read_byte!() as usize;
+                    self.save_ip(ip);
+                    self.invoke(name, arg_count).map_err(error)?;
+                    (chunk, ip, slots) = self.load_frame();
+                }
+                OpCode::SuperInvoke => {
+                    let name = read_string!();
+                    let arg_count = read_byte!() as usize;
+                    let superclass = self.pop().as_obj().expect("superclass");
+                    self.save_ip(ip);
+                    self.invoke_from_class(superclass, name, arg_count).map_err(error)?;
+                    (chunk, ip, slots) = self.load_frame();
+                }
+                OpCode::Closure => {
+                    let function = read_constant!().as_obj().expect("function constant");
+                    let upvalue_count = self.heap.function(function).upvalue_count;
+
+                    let mut upvalues = Vec::with_capacity(upvalue_count);
+                    for _ in 0..upvalue_count {
+                        let is_local = read_byte!() == 1;
+                        let index = read_byte!();
+                        let upvalue = if is_local {
+                            self.capture_upvalue(slots + index as usize)
+                        } else {
+                            self.frame_upvalue(index)
+                        };
+                        upvalues.push(upvalue);
+                    }
+
+                    let closure = self.alloc(Obj::Closure(Closure { function, upvalues }));
+                    self.push(Value::Obj(closure));
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    self.close_upvalues(slots);
+                    self.frames.pop();
+                    if self.frames.is_empty() {
+                        // Pop the script's closure.
+                        self.pop();
+                        return Ok(());
+                    }
+
+                    self.stack.truncate(slots);
+                    self.push(result);
+                    (chunk, ip, slots) = self.load_frame();
+                }
+                OpCode::Class => {
+                    let name = read_string!();
+                    let class = self.alloc(Obj::Class(Class { name, methods: HashMap::new() }));
+                    self.push(Value::Obj(class));
+                }
+                OpCode::Inherit => {
+                    let superclass = match self.peek(1) {
+                        Value::Obj(obj) if matches!(self.heap.get(obj), Obj::Class(_)) => obj,
+                        _ => return Err(error(String::from("Superclass must be a class."))),
+                    };
+                    let subclass = self.peek(0).as_obj().expect("subclass");
+                    self.heap.inherit(subclass, superclass);
+                    self.pop();
+                }
+                OpCode::Method => {
+                    let name = read_string!();
+                    let method = self.peek(0).as_obj().expect("method closure");
+                    let class = self.peek(1).as_obj().expect("class");
+                    self.heap.set_method(class, name, method);
+                    self.pop();
+                }
+            }
+        }
+    }
+
+    fn load_frame(&self) -> (Rc<Chunk>, usize, usize) {
+        let frame = self.frames.last().expect("no active call frame");
+        (Rc::clone(&frame.chunk), frame.ip, frame.slots)
+    }
+
+    fn save_ip(&mut self, ip: usize) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.ip = ip;
+        }
+    }
+
+    fn frame_upvalue(&self, index: u8) -> ObjRef {
+        let closure = self.frames.last().expect("no active call frame").closure;
+        self.heap.closure(closure).upvalues[index as usize]
+    }
+
+    fn call_value(&mut self, callee: Value, arg_count: usize) -> Result<(), String> {
+        let Value::Obj(callee) = callee else {
+            return Err(String::from("Can only call functions and classes."));
+        };
+
+        match self.heap.get(callee) {
+            Obj::Closure(_) => self.call_closure(callee, arg_count),
+            Obj::Native(native) => {
+                check_arity(native.arity, arg_count)?;
+                let function = native.function;
+
+                let args_start = self.stack.len() - arg_count;
+                let result = function(&self.stack[args_start..]);
+                self.stack.truncate(args_start - 1);
+                self.push(result);
+                Ok(())
+            }
+            Obj::Class(class) => {
+                let initializer = class.methods.get(&self.init_string).copied();
+
+                // The class is still in the callee slot, so it is rooted while
+                // the instance is allocated.
+                let instance = self.alloc(Obj::Instance(Instance { class: callee, fields: HashMap::new() }));
+                let callee_slot = self.stack.len() - arg_count - 1;
+                self.stack[callee_slot] = Value::Obj(instance);
+
+                match initializer {
+                    Some(initializer) => self.call_closure(initializer, arg_count),
+                    None => check_arity(0, arg_count),
+                }
+            }
+            Obj::BoundMethod(BoundMethod { receiver, method }) => {
+                let method = *method;
+                let callee_slot = self.stack.len() - arg_count - 1;
+                self.stack[callee_slot] = *receiver;
+                self.call_closure(method, arg_count)
+            }
+            _ => Err(String::from("Can only call functions and classes.")),
+        }
+    }
+
+    fn call_closure(&mut self, closure: ObjRef, arg_count: usize) -> Result<(), String> {
+        let function = self.heap.function(self.heap.closure(closure).function);
+        check_arity(function.arity, arg_count)?;
+
+        if self.frames.len() == FRAMES_MAX {
+            return Err(String::from("Stack overflow."));
+        }
+
+        self.frames.push(CallFrame {
+            closure,
+            chunk: Rc::clone(&function.chunk),
+            ip: 0,
+            slots: self.stack.len() - arg_count - 1,
+        });
+        Ok(())
+    }
+
+    /// Calls a method straight off the receiver, without allocating the
+    /// bound method that `GetProperty` followed by `Call` would need.
+    fn invoke(&mut self, name: ObjRef, arg_count: usize) -> Result<(), String> {
+        let receiver = self.peek(arg_count);
+        let Some(instance) = self.heap.as_instance(receiver) else {
+            return Err(String::from("Only instances have properties."));
+        };
+
+        // A field holding a function shadows a method of the same name.
+        if let Some(&field) = instance.fields.get(&name) {
+            let callee_slot = self.stack.len() - arg_count - 1;
+            self.stack[callee_slot] = field;
+            return self.call_value(field, arg_count);
+        }
+
+        self.invoke_from_class(instance.class, name, arg_count)
+    }
+
+    fn invoke_from_class(&mut self, class: ObjRef, name: ObjRef, arg_count: usize) -> Result<(), String> {
+        match self.heap.class(class).methods.get(&name) {
+            Some(&method) => self.call_closure(method, arg_count),
+            None => Err(self.undefined_property(name)),
+        }
+    }
+
+    /// Replaces the receiver on top of the stack with its method `name`,
+    /// bound to it.
+    fn bind_method(&mut self, class: ObjRef, name: ObjRef) -> Result<(), String> {
+        let Some(&method) = self.heap.class(class).methods.get(&name) else {
+            return Err(self.undefined_property(name));
+        };
+
+        let bound = self.alloc(Obj::BoundMethod(BoundMethod { receiver: self.peek(0), method }));
+        self.pop();
+        self.push(Value::Obj(bound));
+        Ok(())
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> ObjRef {
+        let position = self.open_upvalues.iter().position(|&upvalue| match self.heap.upvalue(upvalue) {
+            Upvalue::Open(open) => *open >= slot,
+            Upvalue::Closed(_) => unreachable!("closed upvalue in the open list"),
+        });
+
+        if let Some(position) = position {
+            let upvalue = self.open_upvalues[position];
+            if matches!(self.heap.upvalue(upvalue), Upvalue::Open(open) if *open == slot) {
+                return upvalue;
+            }
+        }
+
+        let upvalue = self.alloc(Obj::Upvalue(Upvalue::Open(slot)));
+        let position = position.unwrap_or(self.open_upvalues.len());
+        self.open_upvalues.insert(position, upvalue);
+        upvalue
+    }
+
+    /// Closes every open upvalue pointing at `last` or above, moving the
+    /// variable off the stack and into the upvalue.
+    fn close_upvalues(&mut self, last: usize) {
+        while let Some(&upvalue) = self.open_upvalues.last() {
+            let slot = match self.heap.upvalue(upvalue) {
+                Upvalue::Open(slot) if *slot >= last => *slot,
+                _ => break,
+            };
+
+            *self.heap.upvalue_mut(upvalue) = Upvalue::Closed(self.stack[slot]);
+            self.open_upvalues.pop();
+        }
+    }
+
+    fn define_native(&mut self, name: &'static str, arity: usize, function: fn(&[Value]) -> Value) {
+        // Both objects are kept on the stack until they are reachable from
+        // the globals.
+        let name_string = self.intern(name);
+        self.push(Value::Obj(name_string));
+        let native = self.alloc(Obj::Native(Native { name, arity, function }));
+        self.push(Value::Obj(native));
+
+        self.globals.insert(name_string, Value::Obj(native));
+        self.pop();
+        self.pop();
+    }
+
+    pub(crate) fn intern(&mut self, text: &str) -> ObjRef {
+        self.intern_with_roots(text, &[])
+    }
+
+    /// Interns `text`, treating `roots` as reachable if the allocation
+    /// triggers a collection. The compiler uses this to keep the constants
+    /// of the functions it is building alive.
+    pub(crate) fn intern_with_roots(&mut self, text: &str, roots: &[ObjRef]) -> ObjRef {
+        if let Some(string) = self.heap.find_string(text) {
+            return string;
+        }
+
+        self.collect_if_needed(roots);
+        self.heap.alloc_string(text.to_string())
+    }
+
+    fn alloc(&mut self, obj: Obj) -> ObjRef {
+        self.alloc_with_roots(obj, &[])
+    }
+
+    /// Moves `obj` onto the heap, first collecting garbage if it is time to.
+    /// Anything `obj` refers to is kept alive by that collection, as is
+    /// everything in `roots`.
+    pub(crate) fn alloc_with_roots(&mut self, obj: Obj, roots: &[ObjRef]) -> ObjRef {
+        if self.heap.should_collect() {
+            let mut references = roots.to_vec();
+            obj.references(&mut references);
+            self.collect_if_needed(&references);
+        }
+        self.heap.alloc(obj)
+    }
+
+    fn collect_if_needed(&mut self, roots: &[ObjRef]) {
+        if self.heap.should_collect() {
+            for &root in roots {
+                self.heap.mark_object(root);
+            }
+            self.collect_garbage();
+        }
+    }
+
+    /// Every global variable's name and value, in no particular order.
+    pub fn globals(&self) -> impl Iterator<Item = (ObjRef, Value)> + '_ {
+        self.globals.iter().map(|(&name, &value)| (name, value))
+    }
+
+    /// Frees every object not reachable from the VM's roots, and returns
+    /// the number of bytes freed.
+    pub fn collect_garbage(&mut self) -> usize {
+        for &value in self.stack.iter() {
+            self.heap.mark_value(value);
+        }
+        for (&name, &value) in self.globals.iter() {
+            self.heap.mark_object(name);
+            self.heap.mark_value(value);
+        }
+        for frame in self.frames.iter() {
+            self.heap.mark_object(frame.closure);
+        }
+        for &upvalue in self.open_upvalues.iter() {
+            self.heap.mark_object(upvalue);
+        }
+        self.heap.mark_object(self.init_string);
+
+        self.heap.collect()
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("VM stack underflow")
+    }
+
+    fn peek(&self, distance: usize) -> Value {
+        self.stack[self.stack.len() - 1 - distance]
+    }
+
+    /// Pops the two operands of a binary instruction, left operand first.
+    fn pop_pair(&mut self) -> (Value, Value) {
+        let b = self.pop();
+        let a = self.pop();
+        (a, b)
+    }
+
+    fn arithmetic(&mut self, op: fn(f64, f64) -> f64) -> Result<(), String> {
+        match self.pop_pair() {
+            (Value::Number(a), Value::Number(b)) => {
+                self.push(Value::Number(op(a, b)));
+                Ok(())
+            }
+            _ => Err(String::from("Operands must be numbers.")),
+        }
+    }
+
+    fn comparison(&mut self, op: fn(f64, f64) -> bool) -> Result<(), String> {
+        match self.pop_pair() {
+            (Value::Number(a), Value::Number(b)) => {
+                self.push(Value::Bool(op(a, b)));
+                Ok(())
+            }
+            _ => Err(String::from("Operands must be numbers.")),
+        }
+    }
+
+    fn undefined_variable(&self, name: ObjRef) -> String {
+        format!("Undefined variable '{}'.", self.heap.string(name))
+    }
+
+    fn undefined_property(&self, name: ObjRef) -> String {
+        format!("Undefined property '{}'.", self.heap.string(name))
+    }
+}
+
+fn check_arity(arity: usize, arg_count: usize) -> Result<(), String> {
+    if arity != arg_count {
+        return Err(format!("Expected {arity} arguments but got {arg_count}."));
+    }
+    Ok(())
+}
+
+fn clock(_arguments: &[Value]) -> Value {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    Value::Number(now.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytecode::compiler::Compiler;
+    use crate::bytecode::object::Function;
+    use crate::reporter::Collector;
+    use crate::scanner::Scanner;
+    use crate::test_support::Output;
+
+    use super::*;
+
+    fn chunk(code: &[(OpCode, Option<Value>)]) -> Chunk {
+        let mut chunk = Chunk::new();
+        for (op, operand) in code {
+            chunk.write_op(*op, 1);
+            if let Some(value) = operand {
+                let constant = chunk.add_constant(*value);
+                chunk.write(constant as u8, 1);
+            }
+        }
+        chunk
+    }
+
+    fn run(chunk: Chunk) -> Result<String, VmError> {
+        let output = Output::default();
+        let mut vm = VM::with_output(Box::new(output.clone()));
+        let function = vm.alloc(Obj::Function(Function { arity: 0, upvalue_count: 0, chunk: Rc::new(chunk), name: None }));
+        vm.interpret(function)?;
+        Ok(output.contents())
+    }
+
+    fn run_source(vm: &mut VM, source: &str) -> Result<(), VmError> {
+        let mut collector = Collector::new();
+        let function = Compiler::new(Scanner::new(source, &mut collector), vm).compile().unwrap();
+        vm.interpret(function)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        // print -(1.2 + 3.4) / 2;
+        let chunk = chunk(&[
+            (OpCode::Constant, Some(Value::Number(1.2))),
+            (OpCode::Constant, Some(Value::Number(3.4))),
+            (OpCode::Add, None),
+            (OpCode::Negate, None),
+            (OpCode::Constant, Some(Value::Number(2.0))),
+            (OpCode::Divide, None),
+            (OpCode::Print, None),
+            (OpCode::Nil, None),
+            (OpCode::Return, None),
+        ]);
+
+        assert_eq!(run(chunk).unwrap(), "-2.3\n");
+    }
+
+    #[test]
+    fn test_comparison_and_not() {
+        // print !(5 - 4 > 3 * 2 == !nil);
+        let chunk = chunk(&[
+            (OpCode::Constant, Some(Value::Number(5.0))),
+            (OpCode::Constant, Some(Value::Number(4.0))),
+            (OpCode::Subtract, None),
+            (OpCode::Constant, Some(Value::Number(3.0))),
+            (OpCode::Constant, Some(Value::Number(2.0))),
+            (OpCode::Multiply, None),
+            (OpCode::Greater, None),
+            (OpCode::Nil, None),
+            (OpCode::Not, None),
+            (OpCode::Equal, None),
+            (OpCode::Not, None),
+            (OpCode::Print, None),
+            (OpCode::Nil, None),
+            (OpCode::Return, None),
+        ]);
+
+        assert_eq!(run(chunk).unwrap(), "true\n");
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let negate = chunk(&[(OpCode::True, None), (OpCode::Negate, None), (OpCode::Return, None)]);
+        assert_eq!(run(negate).unwrap_err(), VmError { message: String::from("Operand must be a number."), line: 1 });
+
+        let less = chunk(&[(OpCode::Nil, None), (OpCode::False, None), (OpCode::Less, None), (OpCode::Return, None)]);
+        assert_eq!(run(less).unwrap_err().message, "Operands must be numbers.");
+    }
+
+    #[test]
+    fn test_collect_garbage_keeps_reachable_objects() {
+        let mut vm = VM::with_output(Box::new(Output::default()));
+        run_source(&mut vm, "var keep = \"kept\" + \"!\"; { var temp = \"temp\" + \"!\"; }").unwrap();
+
+        vm.collect_garbage();
+        assert!(vm.heap.find_string("kept!").is_some());
+        assert!(vm.heap.find_string("temp!").is_none());
+    }
+
+    #[test]
+    fn test_collect_garbage_frees_cycles() {
+        let mut vm = VM::with_output(Box::new(Output::default()));
+        run_source(&mut vm, "class Node {} fun cycle() { var a = Node(); var b = Node(); a.next = b; b.next = a; } cycle();").unwrap();
+        vm.collect_garbage();
+        let baseline = vm.heap.len();
+
+        run_source(&mut vm, "for (var i = 0; i < 100; i = i + 1) cycle();").unwrap();
+        vm.collect_garbage();
+        assert_eq!(vm.heap.len(), baseline);
+    }
+
+    #[test]
+    fn test_gc_stress() {
+        let output = Output::default();
+        let mut vm = VM::with_output(Box::new(output.clone()));
+        vm.set_gc_stress(true);
+
+        let source = "
+            class List { init(head, tail) { this.head = head; this.tail = tail; } }
+            fun build(n) { var list = nil; for (var i = 0; i < n; i = i + 1) list = List(\"item\" + \"!\", list); return list; }
+            var list = build(50);
+            var count = 0;
+            while (list != nil) { count = count + 1; list = list.tail; }
+            print count;";
+        run_source(&mut vm, source).unwrap();
+        assert_eq!(output.contents(), "50\n");
+    }
+}
@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::class::LoxInstance;
+use crate::environment::Environment;
+use crate::error::RuntimeError;
+use crate::interpreter::{Interpreter, Unwind};
+use crate::statements::stmt::Function;
+use crate::symbol::Symbol;
+use crate::value::Value;
+
+pub trait Callable<'src> {
+    fn arity(&self) -> usize;
+
+    fn call(&self, interpreter: &Interpreter<'src>, arguments: Vec<Value<'src>>) -> Result<Value<'src>, RuntimeError<'src>>;
+}
+
+/// A user-defined function together with the environment it was declared in.
+pub struct LoxFunction<'src> {
+    declaration: Rc<Function<'src>>,
+    closure: Rc<RefCell<Environment<'src>>>,
+    is_initializer: bool,
+}
+
+impl<'src> LoxFunction<'src> {
+    pub fn new(declaration: Rc<Function<'src>>, closure: Rc<RefCell<Environment<'src>>>, is_initializer: bool) -> Self {
+        LoxFunction {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Returns a copy of this method whose closure has `this` bound to `instance`.
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance<'src>>>) -> LoxFunction<'src> {
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        environment.define(Symbol::THIS, Value::Instance(instance));
+        LoxFunction::new(Rc::clone(&self.declaration), Rc::new(RefCell::new(environment)), self.is_initializer)
+    }
+
+    fn this(&self) -> Value<'src> {
+        self.closure.borrow().get_local(Symbol::THIS).unwrap_or(Value::Nil)
+    }
+}
+
+impl<'src> Callable<'src> for LoxFunction<'src> {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(&self, interpreter: &Interpreter<'src>, arguments: Vec<Value<'src>>) -> Result<Value<'src>, RuntimeError<'src>> {
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(param.symbol(), argument);
+        }
+
+        match interpreter.execute_block(&self.declaration.body, environment) {
+            // An initializer always hands back the instance, even on a bare `return;`.
+            Ok(()) | Err(Unwind::Return(_)) if self.is_initializer => Ok(self.this()),
+            Ok(()) => Ok(Value::Nil),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(error)) => Err(error),
+        }
+    }
+}
+
+impl fmt::Debug for LoxFunction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl fmt::Display for LoxFunction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
+
+/// A function implemented in Rust and exposed to Lox as a global.
+pub struct NativeFunction<'src> {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: fn(&[Value<'src>]) -> Value<'src>,
+}
+
+impl<'src> Callable<'src> for NativeFunction<'src> {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, _interpreter: &Interpreter<'src>, arguments: Vec<Value<'src>>) -> Result<Value<'src>, RuntimeError<'src>> {
+        Ok((self.function)(&arguments))
+    }
+}
+
+impl fmt::Debug for NativeFunction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl fmt::Display for NativeFunction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
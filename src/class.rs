@@ -0,0 +1,108 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::callable::{Callable, LoxFunction};
+use crate::error::RuntimeError;
+use crate::interpreter::Interpreter;
+use crate::symbol::Symbol;
+use crate::token::Token;
+use crate::value::Value;
+
+pub struct LoxClass<'src> {
+    pub name: Symbol,
+    superclass: Option<Rc<LoxClass<'src>>>,
+    methods: HashMap<Symbol, Rc<LoxFunction<'src>>>,
+}
+
+impl<'src> LoxClass<'src> {
+    pub fn new(name: Symbol, superclass: Option<Rc<LoxClass<'src>>>, methods: HashMap<Symbol, Rc<LoxFunction<'src>>>) -> Self {
+        LoxClass {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    /// Finds a method on this class or, failing that, up the superclass chain.
+    pub fn find_method(&self, name: Symbol) -> Option<Rc<LoxFunction<'src>>> {
+        match self.methods.get(&name) {
+            Some(method) => Some(Rc::clone(method)),
+            None => self.superclass.as_ref().and_then(|superclass| superclass.find_method(name)),
+        }
+    }
+}
+
+/// Calling a class creates a new instance, so the callable needs the shared
+/// handle to the class rather than a plain reference.
+impl<'src> Callable<'src> for Rc<LoxClass<'src>> {
+    fn arity(&self) -> usize {
+        self.find_method(Symbol::INIT).map_or(0, |initializer| initializer.arity())
+    }
+
+    fn call(&self, interpreter: &Interpreter<'src>, arguments: Vec<Value<'src>>) -> Result<Value<'src>, RuntimeError<'src>> {
+        let instance = Rc::new(RefCell::new(LoxInstance::new(Rc::clone(self))));
+
+        if let Some(initializer) = self.find_method(Symbol::INIT) {
+            initializer.bind(Rc::clone(&instance)).call(interpreter, arguments)?;
+        }
+
+        Ok(Value::Instance(instance))
+    }
+}
+
+impl fmt::Debug for LoxClass<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+impl fmt::Display for LoxClass<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub struct LoxInstance<'src> {
+    class: Rc<LoxClass<'src>>,
+    fields: HashMap<Symbol, Value<'src>>,
+}
+
+impl<'src> LoxInstance<'src> {
+    pub fn new(class: Rc<LoxClass<'src>>) -> Self {
+        LoxInstance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Looks up a field first, then a method bound to `instance`.
+    pub fn get(instance: &Rc<RefCell<LoxInstance<'src>>>, name: &Token<'src>) -> Result<Value<'src>, RuntimeError<'src>> {
+        if let Some(value) = instance.borrow().fields.get(&name.symbol()) {
+            return Ok(value.clone());
+        }
+
+        let method = instance.borrow().class.find_method(name.symbol());
+        match method {
+            Some(method) => Ok(Value::Function(Rc::new(method.bind(Rc::clone(instance))))),
+            None => Err(RuntimeError::new(name, &format!("Undefined property '{}'.", name.lexeme))),
+        }
+    }
+
+    pub fn set(&mut self, name: &Token<'src>, value: Value<'src>) {
+        self.fields.insert(name.symbol(), value);
+    }
+}
+
+impl fmt::Debug for LoxInstance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} instance>", self.class.name)
+    }
+}
+
+impl fmt::Display for LoxInstance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
@@ -0,0 +1,246 @@
+use std::fmt::Write;
+
+use crate::span::Span;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A problem found in a Lox program, with enough location information to
+/// show the user the offending source.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+impl Diagnostic {
+    pub fn error(message: &str, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.to_string(),
+            primary: Label { span, message: String::new() },
+            secondary: vec![],
+            notes: vec![],
+            help: None,
+        }
+    }
+
+    pub fn with_primary_label(mut self, message: &str) -> Self {
+        self.primary.message = message.to_string();
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: &str) -> Self {
+        self.secondary.push(Label { span, message: message.to_string() });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    /// Renders the diagnostic with the source lines it points at, e.g.
+    ///
+    /// ```text
+    /// error: Unterminated string
+    ///  --> 1:9
+    ///   |
+    /// 1 | print "hello
+    ///   |       ^ string starts here
+    /// ```
+    pub fn render(&self, source: &str, color: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("{style}{text}{RESET}")
+            } else {
+                text.to_string()
+            }
+        };
+
+        let lines = source.lines().collect::<Vec<_>>();
+        let last_line = self.labels().map(|label| label.span.end_line).max().unwrap_or(1);
+        let width = last_line.to_string().len();
+        let gutter = paint(BLUE, &format!("{:width$} |", ""));
+
+        let mut out = String::new();
+        writeln!(out, "{}{}", paint(self.severity.color(), self.severity.name()), paint(BOLD, &format!(": {}", self.message))).unwrap();
+        writeln!(out, "{}{} {}:{}", " ".repeat(width), paint(BLUE, "-->"), self.primary.span.start_line, self.primary.span.start_column).unwrap();
+        writeln!(out, "{gutter}").unwrap();
+
+        let mut labels = self.labels().collect::<Vec<_>>();
+        labels.sort_by_key(|label| (label.span.start_line, label.span.start_column));
+
+        let mut printed_through = 0;
+        for label in labels {
+            let is_primary = std::ptr::eq(label, &self.primary);
+            let (marker, style) = if is_primary { ('^', self.severity.color()) } else { ('-', BLUE) };
+
+            let first = label.span.start_line.max(1);
+            let last = label.span.end_line.max(first);
+            for line_number in first..=last {
+                let text = lines.get(line_number as usize - 1).copied().unwrap_or("");
+                if line_number > printed_through {
+                    if printed_through > 0 && line_number > printed_through + 1 {
+                        writeln!(out, "{}", paint(BLUE, "...")).unwrap();
+                    }
+                    writeln!(out, "{} {}", paint(BLUE, &format!("{line_number:width$} |")), text).unwrap();
+                    printed_through = line_number;
+                }
+
+                let line_length = text.chars().count() as u32;
+                let from = if line_number == first { label.span.start_column.max(1) } else { 1 };
+                let to = if line_number == last { label.span.end_column } else { line_length + 1 };
+                let underline = marker.to_string().repeat(to.saturating_sub(from).max(1) as usize);
+
+                let message = if line_number == last && !label.message.is_empty() {
+                    format!(" {}", label.message)
+                } else {
+                    String::new()
+                };
+                writeln!(out, "{gutter} {}{}", " ".repeat(from as usize - 1), paint(style, &format!("{underline}{message}"))).unwrap();
+            }
+        }
+
+        for note in self.notes.iter() {
+            writeln!(out, "{}{} {}", " ".repeat(width + 1), paint(BOLD, "= note:"), note).unwrap();
+        }
+        if let Some(help) = &self.help {
+            writeln!(out, "{}{} {}", " ".repeat(width + 1), paint(BOLD, "= help:"), help).unwrap();
+        }
+
+        out
+    }
+
+    fn labels(&self) -> impl Iterator<Item = &Label> {
+        std::iter::once(&self.primary).chain(self.secondary.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start_line: u32, start_column: u32, end_line: u32, end_column: u32) -> Span {
+        Span { start_line, start_column, end_line, end_column, ..Span::default() }
+    }
+
+    #[test]
+    fn test_render_single_line() {
+        let diagnostic = Diagnostic::error("Unexpected character.", span(2, 9, 2, 10))
+            .with_primary_label("not valid here");
+        let source = "var a = 1;\nvar b = @;\n";
+
+        assert_eq!(diagnostic.render(source, false), "\
+error: Unexpected character.
+ --> 2:9
+  |
+2 | var b = @;
+  |         ^ not valid here
+");
+    }
+
+    #[test]
+    fn test_render_multi_line_span() {
+        let diagnostic = Diagnostic::error("Unterminated block comment", span(1, 3, 2, 4));
+        let source = "1 /* one\ntwo";
+
+        assert_eq!(diagnostic.render(source, false), "\
+error: Unterminated block comment
+ --> 1:3
+  |
+1 | 1 /* one
+  |   ^^^^^^
+2 | two
+  | ^^^
+");
+    }
+
+    #[test]
+    fn test_render_secondary_labels_notes_and_help() {
+        let diagnostic = Diagnostic::error("Expect ')' after expression.", span(1, 13, 1, 14))
+            .with_label(span(1, 7, 1, 8), "to match this '('")
+            .with_note("groupings must be closed on the same statement")
+            .with_help("insert ')'");
+        let source = "print (1 + 2;";
+
+        assert_eq!(diagnostic.render(source, false), "\
+error: Expect ')' after expression.
+ --> 1:13
+  |
+1 | print (1 + 2;
+  |       - to match this '('
+  |             ^
+  = note: groupings must be closed on the same statement
+  = help: insert ')'
+");
+    }
+
+    #[test]
+    fn test_render_elides_lines_between_labels() {
+        let diagnostic = Diagnostic::error("Unterminated string", span(1, 7, 1, 8))
+            .with_primary_label("string starts here")
+            .with_label(span(3, 2, 3, 2), "input ends here");
+        let source = "print \"a\nb\nc";
+
+        assert_eq!(diagnostic.render(source, false), "\
+error: Unterminated string
+ --> 1:7
+  |
+1 | print \"a
+  |       ^ string starts here
+...
+3 | c
+  |  - input ends here
+");
+    }
+
+    #[test]
+    fn test_render_with_color() {
+        let diagnostic = Diagnostic::error("Oops.", span(1, 1, 1, 2));
+
+        let rendered = diagnostic.render("x", true);
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+    }
+}
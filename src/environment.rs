@@ -0,0 +1,144 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::RuntimeError;
+use crate::symbol::Symbol;
+use crate::token::Token;
+use crate::value::Value;
+
+#[derive(Debug, Default)]
+pub struct Environment<'src> {
+    values: HashMap<Symbol, Value<'src>>,
+    enclosing: Option<Rc<RefCell<Environment<'src>>>>,
+}
+
+impl<'src> Environment<'src> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment<'src>>>) -> Self {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(&mut self, name: Symbol, value: Value<'src>) {
+        self.values.insert(name, value);
+    }
+
+    /// The variables defined in this scope only, in no particular order.
+    pub fn values(&self) -> impl Iterator<Item = (Symbol, &Value<'src>)> {
+        self.values.iter().map(|(name, value)| (*name, value))
+    }
+
+    /// Looks `name` up in this scope only, without walking the enclosing chain.
+    pub fn get_local(&self, name: Symbol) -> Option<Value<'src>> {
+        self.values.get(&name).cloned()
+    }
+
+    pub fn get(&self, name: &Token<'src>) -> Result<Value<'src>, RuntimeError<'src>> {
+        if let Some(value) = self.values.get(&name.symbol()) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    pub fn assign(&mut self, name: &Token<'src>, value: Value<'src>) -> Result<(), RuntimeError<'src>> {
+        if let Some(slot) = self.values.get_mut(&name.symbol()) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+}
+
+impl<'src> Environment<'src> {
+    /// Reads `name` from the scope exactly `distance` hops up the chain, as
+    /// computed by the resolver.
+    pub fn get_at(environment: &Rc<RefCell<Environment<'src>>>, distance: usize, name: &Token<'src>) -> Result<Value<'src>, RuntimeError<'src>> {
+        Environment::ancestor(environment, distance).borrow().values.get(&name.symbol())
+            .cloned()
+            .ok_or_else(|| undefined_variable(name))
+    }
+
+    pub fn assign_at(environment: &Rc<RefCell<Environment<'src>>>, distance: usize, name: &Token<'src>, value: Value<'src>) {
+        Environment::ancestor(environment, distance).borrow_mut().define(name.symbol(), value);
+    }
+
+    fn ancestor(environment: &Rc<RefCell<Environment<'src>>>, distance: usize) -> Rc<RefCell<Environment<'src>>> {
+        let mut environment = Rc::clone(environment);
+        for _ in 0..distance {
+            let enclosing = environment.borrow().enclosing.clone()
+                .expect("resolver distance exceeds environment depth");
+            environment = enclosing;
+        }
+        environment
+    }
+}
+
+fn undefined_variable<'src>(name: &Token<'src>) -> RuntimeError<'src> {
+    RuntimeError::new(name, &format!("Undefined variable '{}'.", name.lexeme))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::span::Span;
+    use crate::token::TokenType;
+
+    use super::*;
+
+    fn identifier(name: &str) -> Token<'_> {
+        Token::new(TokenType::Identifier, name, None, Span::line(1))
+    }
+
+    #[test]
+    fn test_lookup_through_enclosing() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define(Symbol::intern("a"), Value::Number(1.0));
+
+        let mut local = Environment::with_enclosing(Rc::clone(&globals));
+        local.define(Symbol::intern("b"), Value::Number(2.0));
+
+        assert_eq!(local.get(&identifier("a")), Ok(Value::Number(1.0)));
+        assert_eq!(local.get(&identifier("b")), Ok(Value::Number(2.0)));
+
+        local.assign(&identifier("a"), Value::Nil).unwrap();
+        assert_eq!(globals.borrow().get(&identifier("a")), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn test_get_at_distance() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define(Symbol::intern("a"), Value::Number(1.0));
+
+        let local = Rc::new(RefCell::new(Environment::with_enclosing(Rc::clone(&globals))));
+        local.borrow_mut().define(Symbol::intern("a"), Value::Number(2.0));
+
+        assert_eq!(Environment::get_at(&local, 0, &identifier("a")), Ok(Value::Number(2.0)));
+        assert_eq!(Environment::get_at(&local, 1, &identifier("a")), Ok(Value::Number(1.0)));
+
+        Environment::assign_at(&local, 1, &identifier("a"), Value::Nil);
+        assert_eq!(globals.borrow().get(&identifier("a")), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let mut environment = Environment::new();
+
+        let error = environment.get(&identifier("x")).unwrap_err();
+        assert_eq!(error.message, "Undefined variable 'x'.");
+
+        assert!(environment.assign(&identifier("x"), Value::Nil).is_err());
+    }
+}
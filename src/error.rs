@@ -1,36 +1,64 @@
-use std::cell::Cell;
+use std::fmt;
 
-pub fn error(line: usize, message: &str) {
-    report(line, "", message);
+use crate::bytecode::vm::VmError;
+use crate::diagnostic::Diagnostic;
+use crate::token::{Token, TokenType};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError<'src> {
+    pub token: Token<'src>,
+    pub message: String,
+}
+
+impl<'src> ParseError<'src> {
+    pub fn new(token: &Token<'src>, message: &str) -> Self {
+        ParseError {
+            token: token.clone(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(&self.message, self.token.span());
+        if self.token.token_type == TokenType::Eof {
+            diagnostic.with_primary_label("at end of input")
+        } else {
+            diagnostic
+        }
+    }
 }
 
-pub fn report(line: usize, place: &str, message: &str) {
-    eprintln!("[line {line}] Error{place}: {message}");
-    set_error(true);
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuntimeError<'src> {
+    pub token: Token<'src>,
+    pub message: String,
 }
 
-thread_local! {
-    static HAS_ERROR: Cell<bool> = Cell::new(false);
+impl<'src> RuntimeError<'src> {
+    pub fn new(token: &Token<'src>, message: &str) -> Self {
+        RuntimeError {
+            token: token.clone(),
+            message: message.to_string(),
+        }
+    }
 }
 
-pub fn set_error(error: bool) {
-    HAS_ERROR.set(error);
+impl fmt::Display for RuntimeError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n[line {}]", self.message, self.token.line())
+    }
 }
 
-pub fn has_error() -> bool {
-    HAS_ERROR.get()
+pub fn runtime_error(error: &RuntimeError<'_>) {
+    report_runtime_error(&error.message, error.token.line());
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Reports an error raised by the bytecode VM the same way `runtime_error`
+/// reports one from the tree-walker, so scripts fail alike on both.
+pub fn vm_runtime_error(error: &VmError) {
+    report_runtime_error(&error.message, error.line as usize);
+}
 
-    #[test]
-    fn test_error() {
-        assert!(!has_error());
-        set_error(true);
-        assert!(has_error());
-        set_error(false);
-        assert!(!has_error());
-    }
+fn report_runtime_error(message: &str, line: usize) {
+    eprintln!("{message}\n[line {line}]");
 }
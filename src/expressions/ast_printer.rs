@@ -2,16 +2,32 @@ use std::fmt::Write;
 
 use crate::expressions::expr::Expr;
 use crate::expressions::Visitor;
-use crate::token::Token;
+use crate::statements;
+use crate::statements::stmt::{Function, Stmt};
+use crate::token::TokenLiteral;
 
 pub struct AstPrinter;
 
 impl AstPrinter {
-    pub fn print(&self, expr: &Expr) -> String {
+    pub fn print(&self, expr: &Expr<'_>) -> String {
         expr.accept(self)
     }
 
-    pub fn parenthesize(&self, op: &str, exprs: Vec<&Expr>) -> String {
+    pub fn print_stmt(&self, stmt: &Stmt<'_>) -> String {
+        stmt.accept(self)
+    }
+
+    fn function(&self, keyword: &str, function: &Function<'_>) -> String {
+        let params = function.params.iter().map(|param| param.lexeme).collect::<Vec<_>>();
+        let mut result = format!("({keyword} {}({})", function.name.lexeme, params.join(" "));
+        for stmt in function.body.iter() {
+            write!(&mut result, " {}", stmt.accept(self)).unwrap();
+        }
+        result.push(')');
+        result
+    }
+
+    pub fn parenthesize(&self, op: &str, exprs: Vec<&Expr<'_>>) -> String {
         let mut result = String::new();
 
         write!(&mut result, "({op}").unwrap();
@@ -25,19 +41,94 @@ impl AstPrinter {
     }
 }
 
-impl Visitor<String> for AstPrinter {
-    fn visit_expr(&self, expr: &Expr) -> String {
+impl<'src> Visitor<'src, String> for AstPrinter {
+    fn visit_expr(&self, expr: &Expr<'src>) -> String {
         match expr {
-            Expr::Binary(lhs, op, rhs) => self.parenthesize(&op.lexeme, vec![lhs, rhs]),
-            Expr::Grouping(exp) => self.parenthesize("group", vec![exp]),
-            Expr::Literal(literal) => literal.to_string(),
-            Expr::Unary(op, rhs) => self.parenthesize(&op.lexeme, vec![rhs]),
+            Expr::Assign(name, value, _) => self.parenthesize(&format!("= {}", name.lexeme), vec![value]),
+            Expr::Binary(lhs, op, rhs) => self.parenthesize(op.lexeme, vec![lhs, rhs]),
+            Expr::Call(callee, _, arguments) => {
+                let mut exprs = vec![callee.as_ref()];
+                exprs.extend(arguments);
+                self.parenthesize("call", exprs)
+            }
+            Expr::Get(object, name) => format!("(. {} {})", object.accept(self), name.lexeme),
+            Expr::Grouping(exp, _) => self.parenthesize("group", vec![exp]),
+            Expr::Interpolation(parts, _) => {
+                // Text is quoted so it stands apart from the expressions.
+                let mut result = String::from("(interpolate");
+                for part in parts {
+                    match part {
+                        Expr::Literal(TokenLiteral::String(text), _) => write!(&mut result, " {:?}", text.as_str()),
+                        _ => write!(&mut result, " {}", part.accept(self)),
+                    }
+                    .unwrap();
+                }
+                result.push(')');
+                result
+            }
+            Expr::Literal(literal, _) => literal.to_string(),
+            Expr::Logical(lhs, op, rhs) => self.parenthesize(op.lexeme, vec![lhs, rhs]),
+            Expr::Set(object, name, value) => {
+                format!("(= (. {} {}) {})", object.accept(self), name.lexeme, value.accept(self))
+            }
+            Expr::Super(_, method, _) => format!("(. super {})", method.lexeme),
+            Expr::This(_, _) => String::from("this"),
+            Expr::Unary(op, rhs) => self.parenthesize(op.lexeme, vec![rhs]),
+            Expr::Variable(name, _) => name.lexeme.to_string(),
+        }
+    }
+}
+
+impl<'src> statements::Visitor<'src, String> for AstPrinter {
+    fn visit_stmt(&self, stmt: &Stmt<'src>) -> String {
+        match stmt {
+            Stmt::Block(statements) => {
+                let mut result = String::from("(block");
+                for stmt in statements {
+                    write!(&mut result, " {}", stmt.accept(self)).unwrap();
+                }
+                result.push(')');
+                result
+            }
+            Stmt::Class(name, superclass, methods) => {
+                let mut result = format!("(class {}", name.lexeme);
+                if let Some(superclass) = superclass {
+                    write!(&mut result, " < {}", superclass.accept(self)).unwrap();
+                }
+                for method in methods {
+                    write!(&mut result, " {}", self.function("method", method)).unwrap();
+                }
+                result.push(')');
+                result
+            }
+            Stmt::Expression(expr) => self.parenthesize(";", vec![expr]),
+            Stmt::Function(function) => self.function("fun", function),
+            Stmt::If(condition, then_branch, else_branch) => match else_branch {
+                Some(else_branch) => format!("(if {} {} {})", condition.accept(self), then_branch.accept(self), else_branch.accept(self)),
+                None => format!("(if {} {})", condition.accept(self), then_branch.accept(self)),
+            },
+            Stmt::Print(expr) => self.parenthesize("print", vec![expr]),
+            Stmt::Return(_, value) => match value {
+                Some(value) => self.parenthesize("return", vec![value]),
+                None => String::from("(return)"),
+            },
+            Stmt::Var(name, initializer) => match initializer {
+                Some(initializer) => self.parenthesize(&format!("var {}", name.lexeme), vec![initializer]),
+                None => format!("(var {})", name.lexeme),
+            },
+            Stmt::While(condition, body) => format!("(while {} {})", condition.accept(self), body.accept(self)),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::parser::Parse;
+    use crate::reporter::Collector;
+    use crate::scanner::Scanner;
+    use crate::span::Span;
+    use crate::token::Token;
+
     use super::*;
 
     #[test]
@@ -47,11 +138,26 @@ mod tests {
         let expr = Expr::Binary(
             Box::new(Expr::Unary(Token::minus(1), Box::new(Expr::number(123_f64)))),
             Token::star(1),
-            Box::new(Expr::Grouping(Box::new(Expr::number(45.67_f64)))),
+            Box::new(Expr::Grouping(Box::new(Expr::number(45.67_f64)), Span::default())),
         );
 
         let printer = AstPrinter;
 
         assert_eq!("(* (- 123) (group 45.67))", printer.print(&expr));
     }
+
+    #[test]
+    fn test_ast_printer_statements() {
+        let mut collector = Collector::new();
+        let source = "var a = 1; fun f(x, y) { return x; } class B < A { m() {} } if (a) print a; else { a; }";
+        let statements = Parse::new(Scanner::new(source, &mut collector)).parse().unwrap();
+
+        let printed = statements.iter().map(|stmt| AstPrinter.print_stmt(stmt)).collect::<Vec<_>>();
+        assert_eq!(printed, vec![
+            "(var a 1)",
+            "(fun f(x y) (return x))",
+            "(class B < A (method m()))",
+            "(if a (print a) (block (; a)))",
+        ]);
+    }
 }
@@ -0,0 +1,223 @@
+use std::cell::{Cell, RefCell};
+use std::fmt::Write;
+
+use crate::expressions::expr::Expr;
+use crate::expressions::Visitor;
+use crate::statements;
+use crate::statements::stmt::{Function, Stmt};
+use crate::token::TokenLiteral;
+
+/// Renders syntax trees as Graphviz `digraph`s, which shows precedence and
+/// associativity far more plainly than `AstPrinter` does for big trees.
+///
+/// Each node is labelled with its operator, literal or name, and its edges
+/// are written in the order of its children. `ordering=out` tells Graphviz to
+/// keep them in that order, so left operands are drawn on the left.
+pub struct DotPrinter {
+    out: RefCell<String>,
+    nodes: Cell<usize>,
+}
+
+// Statements are boxes, so they stand apart from the expressions in them.
+const STATEMENT: &str = "box";
+const EXPRESSION: &str = "ellipse";
+
+impl DotPrinter {
+    /// The graph of a single expression.
+    pub fn print(expr: &Expr<'_>) -> String {
+        let printer = DotPrinter::new();
+        expr.accept(&printer);
+        printer.finish()
+    }
+
+    /// The graph of a whole program, with every statement under one root.
+    pub fn print_program(statements: &[Stmt<'_>]) -> String {
+        let printer = DotPrinter::new();
+        let root = printer.node("program", STATEMENT);
+        printer.stmts(root, statements);
+        printer.finish()
+    }
+
+    fn new() -> Self {
+        let out = String::from("digraph ast {\n  ordering=out;\n  node [fontname=\"monospace\"];\n");
+        DotPrinter { out: RefCell::new(out), nodes: Cell::new(0) }
+    }
+
+    fn finish(self) -> String {
+        let mut out = self.out.into_inner();
+        out.push_str("}\n");
+        out
+    }
+
+    /// Writes a new node and returns its id.
+    fn node(&self, label: &str, shape: &str) -> usize {
+        let id = self.nodes.get();
+        self.nodes.set(id + 1);
+
+        let mut escaped = String::new();
+        for c in label.chars() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                c => escaped.push(c),
+            }
+        }
+        writeln!(self.out.borrow_mut(), "  n{id} [label=\"{escaped}\", shape={shape}];").unwrap();
+        id
+    }
+
+    fn edge(&self, from: usize, to: usize) {
+        writeln!(self.out.borrow_mut(), "  n{from} -> n{to};").unwrap();
+    }
+
+    /// Writes a node with an edge to each of `children` in turn.
+    fn expr(&self, label: &str, children: &[&Expr<'_>]) -> usize {
+        let id = self.node(label, EXPRESSION);
+        for child in children {
+            let child = child.accept(self);
+            self.edge(id, child);
+        }
+        id
+    }
+
+    fn stmt(&self, label: &str, exprs: &[&Expr<'_>]) -> usize {
+        let id = self.node(label, STATEMENT);
+        for expr in exprs {
+            let child = expr.accept(self);
+            self.edge(id, child);
+        }
+        id
+    }
+
+    fn stmts(&self, parent: usize, statements: &[Stmt<'_>]) {
+        for stmt in statements {
+            let child = stmt.accept(self);
+            self.edge(parent, child);
+        }
+    }
+
+    fn function(&self, keyword: &str, function: &Function<'_>) -> usize {
+        let params = function.params.iter().map(|param| param.lexeme).collect::<Vec<_>>();
+        let id = self.node(&format!("{keyword} {}({})", function.name.lexeme, params.join(", ")), STATEMENT);
+        self.stmts(id, &function.body);
+        id
+    }
+}
+
+impl<'src> Visitor<'src, usize> for DotPrinter {
+    fn visit_expr(&self, expr: &Expr<'src>) -> usize {
+        match expr {
+            Expr::Assign(name, value, _) => self.expr(&format!("{} =", name.lexeme), &[value]),
+            Expr::Binary(lhs, op, rhs) | Expr::Logical(lhs, op, rhs) => self.expr(op.lexeme, &[lhs, rhs]),
+            Expr::Call(callee, _, arguments) => {
+                let mut children = vec![callee.as_ref()];
+                children.extend(arguments);
+                self.expr("call", &children)
+            }
+            Expr::Get(object, name) => self.expr(&format!(".{}", name.lexeme), &[object]),
+            Expr::Grouping(inner, _) => self.expr("( )", &[inner]),
+            Expr::Interpolation(parts, _) => self.expr("interpolate", &parts.iter().collect::<Vec<_>>()),
+            Expr::Literal(TokenLiteral::String(text), _) => self.expr(&format!("{:?}", text.as_str()), &[]),
+            Expr::Literal(literal, _) => self.expr(&literal.to_string(), &[]),
+            Expr::Set(object, name, value) => self.expr(&format!(".{} =", name.lexeme), &[object, value]),
+            Expr::Super(_, method, _) => self.expr(&format!("super.{}", method.lexeme), &[]),
+            Expr::This(..) => self.expr("this", &[]),
+            Expr::Unary(op, rhs) => self.expr(op.lexeme, &[rhs]),
+            Expr::Variable(name, _) => self.expr(name.lexeme, &[]),
+        }
+    }
+}
+
+impl<'src> statements::Visitor<'src, usize> for DotPrinter {
+    fn visit_stmt(&self, stmt: &Stmt<'src>) -> usize {
+        match stmt {
+            Stmt::Block(statements) => {
+                let id = self.node("block", STATEMENT);
+                self.stmts(id, statements);
+                id
+            }
+            Stmt::Class(name, superclass, methods) => {
+                let id = match superclass {
+                    Some(superclass) => self.stmt(&format!("class {} <", name.lexeme), &[superclass]),
+                    None => self.stmt(&format!("class {}", name.lexeme), &[]),
+                };
+                for method in methods {
+                    let child = self.function("method", method);
+                    self.edge(id, child);
+                }
+                id
+            }
+            Stmt::Expression(expr) => self.stmt(";", &[expr]),
+            Stmt::Function(function) => self.function("fun", function),
+            Stmt::If(condition, then_branch, else_branch) => {
+                let id = self.stmt("if", &[condition]);
+                let child = then_branch.accept(self);
+                self.edge(id, child);
+                if let Some(else_branch) = else_branch {
+                    let child = else_branch.accept(self);
+                    self.edge(id, child);
+                }
+                id
+            }
+            Stmt::Print(expr) => self.stmt("print", &[expr]),
+            Stmt::Return(_, value) => self.stmt("return", &value.iter().collect::<Vec<_>>()),
+            Stmt::Var(name, initializer) => {
+                self.stmt(&format!("var {}", name.lexeme), &initializer.iter().collect::<Vec<_>>())
+            }
+            Stmt::While(condition, body) => {
+                let id = self.stmt("while", &[condition]);
+                let child = body.accept(self);
+                self.edge(id, child);
+                id
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::Parse;
+    use crate::reporter::Collector;
+    use crate::scanner::Scanner;
+
+    use super::*;
+
+    fn parse(source: &str) -> Vec<Stmt<'_>> {
+        let mut collector = Collector::new();
+        Parse::new(Scanner::new(source, &mut collector)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_precedence_and_order() {
+        let statements = parse("1 - 2 * \"a\";");
+        let Stmt::Expression(expr) = &statements[0] else { panic!("expected an expression") };
+
+        assert_eq!(DotPrinter::print(expr), "\
+digraph ast {
+  ordering=out;
+  node [fontname=\"monospace\"];
+  n0 [label=\"-\", shape=ellipse];
+  n1 [label=\"1\", shape=ellipse];
+  n0 -> n1;
+  n2 [label=\"*\", shape=ellipse];
+  n3 [label=\"2\", shape=ellipse];
+  n2 -> n3;
+  n4 [label=\"\\\"a\\\"\", shape=ellipse];
+  n2 -> n4;
+  n0 -> n2;
+}
+");
+    }
+
+    #[test]
+    fn test_program() {
+        let statements = parse("var a = 1; fun f(x, y) { return x; } if (a) print a; else a = nil;");
+        let dot = DotPrinter::print_program(&statements);
+
+        assert!(dot.contains("n0 [label=\"program\", shape=box];"));
+        assert!(dot.contains("[label=\"fun f(x, y)\", shape=box];"));
+        assert!(dot.contains("[label=\"a =\", shape=ellipse];"));
+        assert_eq!(dot.matches(" -> ").count(), dot.matches("[label=").count() - 1);
+    }
+}
@@ -1,22 +1,66 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::expressions::Visitor;
+use crate::span::Span;
 use crate::token::{Token, TokenLiteral};
 
-pub enum Expr {
-    Binary(Box<Expr>, Token, Box<Expr>),
-    Grouping(Box<Expr>),
-    Literal(TokenLiteral),
-    Unary(Token, Box<Expr>),
+/// Identifies a variable-like expression, so that passes such as the resolver
+/// can attach facts to a node without holding a reference into the tree.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct ExprId(usize);
+
+impl ExprId {
+    pub fn unique() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        ExprId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
 }
 
-impl Expr {
+#[derive(Debug, PartialEq)]
+pub enum Expr<'src> {
+    Assign(Token<'src>, Box<Expr<'src>>, ExprId),
+    Binary(Box<Expr<'src>>, Token<'src>, Box<Expr<'src>>),
+    Call(Box<Expr<'src>>, Token<'src>, Vec<Expr<'src>>),
+    Get(Box<Expr<'src>>, Token<'src>),
+    Grouping(Box<Expr<'src>>, Span),
+    // The literal text and embedded expressions of an interpolated string,
+    // in order. Empty pieces of text are left out.
+    Interpolation(Vec<Expr<'src>>, Span),
+    Literal(TokenLiteral, Span),
+    Logical(Box<Expr<'src>>, Token<'src>, Box<Expr<'src>>),
+    Set(Box<Expr<'src>>, Token<'src>, Box<Expr<'src>>),
+    Super(Token<'src>, Token<'src>, ExprId),
+    This(Token<'src>, ExprId),
+    Unary(Token<'src>, Box<Expr<'src>>),
+    Variable(Token<'src>, ExprId),
+}
+
+impl<'src> Expr<'src> {
     pub fn accept<V, T>(&self, visitor: &V) -> T
     where
-        V: Visitor<T>,
+        V: Visitor<'src, T>,
     {
         visitor.visit_expr(self)
     }
 
+    /// The source region covered by this expression and all of its children.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Assign(name, value, _) => name.span().to(value.span()),
+            Expr::Binary(lhs, _, rhs) | Expr::Logical(lhs, _, rhs) => lhs.span().to(rhs.span()),
+            Expr::Call(callee, paren, _) => callee.span().to(paren.span()),
+            Expr::Get(object, name) => object.span().to(name.span()),
+            Expr::Grouping(_, span) | Expr::Interpolation(_, span) | Expr::Literal(_, span) => *span,
+            Expr::Set(object, _, value) => object.span().to(value.span()),
+            Expr::Super(keyword, method, _) => keyword.span().to(method.span()),
+            Expr::This(keyword, _) => keyword.span(),
+            Expr::Unary(op, rhs) => op.span().to(rhs.span()),
+            Expr::Variable(name, _) => name.span(),
+        }
+    }
+
+    #[cfg(test)]
     pub fn number(num: f64) -> Self {
-        Expr::Literal(TokenLiteral::Number(num))
+        Expr::Literal(TokenLiteral::Number(num), Span::default())
     }
 }
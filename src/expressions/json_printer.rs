@@ -0,0 +1,237 @@
+use crate::expressions::expr::Expr;
+use crate::expressions::Visitor;
+use crate::json::Json;
+use crate::span::Span;
+use crate::statements;
+use crate::statements::stmt::{Function, Stmt};
+use crate::token::{Token, TokenLiteral};
+
+/// The version of the JSON layout written by `JsonPrinter`. Bump it whenever
+/// a field is added, removed or changes meaning, so tools can tell.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Exports tokens and syntax trees as JSON for tools outside this crate.
+///
+/// Every expression node has the fields `kind`, `operator`, `literal` and
+/// `span`, in that order and `null` where they don't apply, followed by its
+/// children. Statements have a `kind` followed by their parts.
+pub struct JsonPrinter;
+
+impl JsonPrinter {
+    /// A document holding every token of a program.
+    pub fn tokens(&self, tokens: &[Token<'_>]) -> Json {
+        document("tokens", tokens.iter().map(token).collect())
+    }
+
+    /// A document holding the syntax tree of a program.
+    pub fn program(&self, statements: &[Stmt<'_>]) -> Json {
+        document("statements", statements.iter().map(|stmt| stmt.accept(self)).collect())
+    }
+
+    pub fn print(&self, expr: &Expr<'_>) -> Json {
+        expr.accept(self)
+    }
+
+    fn exprs(&self, exprs: &[Expr<'_>]) -> Json {
+        Json::Array(exprs.iter().map(|expr| expr.accept(self)).collect())
+    }
+
+    fn stmts(&self, statements: &[Stmt<'_>]) -> Json {
+        Json::Array(statements.iter().map(|stmt| stmt.accept(self)).collect())
+    }
+
+    fn function(&self, function: &Function<'_>) -> Json {
+        Json::Object(vec![
+            ("kind", Json::string("Function")),
+            ("name", Json::string(function.name.lexeme)),
+            ("params", Json::Array(function.params.iter().map(|param| Json::string(param.lexeme)).collect())),
+            ("body", self.stmts(&function.body)),
+        ])
+    }
+}
+
+fn document(name: &'static str, items: Vec<Json>) -> Json {
+    Json::Object(vec![
+        ("version", Json::Number(SCHEMA_VERSION as f64)),
+        (name, Json::Array(items)),
+    ])
+}
+
+fn token(token: &Token<'_>) -> Json {
+    Json::Object(vec![
+        ("type", Json::String(token.token_type.to_string())),
+        ("lexeme", Json::string(token.lexeme)),
+        ("literal", token.literal.map(literal).into()),
+        ("line", Json::Number(token.line() as f64)),
+        ("column", Json::Number(token.column() as f64)),
+    ])
+}
+
+fn literal(literal: TokenLiteral) -> Json {
+    match literal {
+        TokenLiteral::String(text) => Json::string(text.as_str()),
+        TokenLiteral::Number(n) => Json::Number(n),
+        TokenLiteral::True => Json::Bool(true),
+        TokenLiteral::False => Json::Bool(false),
+        TokenLiteral::Nil => Json::Null,
+    }
+}
+
+fn span(span: Span) -> Json {
+    Json::Object(vec![
+        ("start", Json::Number(span.start as f64)),
+        ("end", Json::Number(span.end as f64)),
+        ("line", Json::Number(span.start_line as f64)),
+        ("column", Json::Number(span.start_column as f64)),
+        ("end_line", Json::Number(span.end_line as f64)),
+        ("end_column", Json::Number(span.end_column as f64)),
+    ])
+}
+
+/// An expression node: the fields every node has, then `children`.
+fn node(kind: &str, operator: Option<&str>, literal: Json, expr: &Expr<'_>, children: Vec<(&'static str, Json)>) -> Json {
+    let mut fields = vec![
+        ("kind", Json::string(kind)),
+        ("operator", operator.map(Json::string).into()),
+        ("literal", literal),
+        ("span", span(expr.span())),
+    ];
+    fields.extend(children);
+    Json::Object(fields)
+}
+
+impl<'src> Visitor<'src, Json> for JsonPrinter {
+    fn visit_expr(&self, expr: &Expr<'src>) -> Json {
+        let name = |token: &Token<'_>| Json::string(token.lexeme);
+        match expr {
+            Expr::Assign(target, value, _) => {
+                node("Assign", Some("="), Json::Null, expr, vec![("name", name(target)), ("value", value.accept(self))])
+            }
+            Expr::Binary(lhs, op, rhs) => {
+                node("Binary", Some(op.lexeme), Json::Null, expr, vec![("left", lhs.accept(self)), ("right", rhs.accept(self))])
+            }
+            Expr::Call(callee, _, arguments) => {
+                node("Call", None, Json::Null, expr, vec![("callee", callee.accept(self)), ("arguments", self.exprs(arguments))])
+            }
+            Expr::Get(object, property) => {
+                node("Get", None, Json::Null, expr, vec![("object", object.accept(self)), ("name", name(property))])
+            }
+            Expr::Grouping(inner, _) => node("Grouping", None, Json::Null, expr, vec![("expression", inner.accept(self))]),
+            Expr::Interpolation(parts, _) => node("Interpolation", None, Json::Null, expr, vec![("parts", self.exprs(parts))]),
+            Expr::Literal(value, _) => node("Literal", None, literal(*value), expr, vec![]),
+            Expr::Logical(lhs, op, rhs) => {
+                node("Logical", Some(op.lexeme), Json::Null, expr, vec![("left", lhs.accept(self)), ("right", rhs.accept(self))])
+            }
+            Expr::Set(object, property, value) => node("Set", Some("="), Json::Null, expr, vec![
+                ("object", object.accept(self)),
+                ("name", name(property)),
+                ("value", value.accept(self)),
+            ]),
+            Expr::Super(_, method, _) => node("Super", None, Json::Null, expr, vec![("method", name(method))]),
+            Expr::This(..) => node("This", None, Json::Null, expr, vec![]),
+            Expr::Unary(op, rhs) => node("Unary", Some(op.lexeme), Json::Null, expr, vec![("operand", rhs.accept(self))]),
+            Expr::Variable(variable, _) => node("Variable", None, Json::Null, expr, vec![("name", name(variable))]),
+        }
+    }
+}
+
+impl<'src> statements::Visitor<'src, Json> for JsonPrinter {
+    fn visit_stmt(&self, stmt: &Stmt<'src>) -> Json {
+        let expr = |expr: &Option<Expr<'src>>| expr.as_ref().map(|expr| expr.accept(self)).into();
+        let fields = match stmt {
+            Stmt::Block(statements) => vec![("kind", Json::string("Block")), ("statements", self.stmts(statements))],
+            Stmt::Class(name, superclass, methods) => vec![
+                ("kind", Json::string("Class")),
+                ("name", Json::string(name.lexeme)),
+                ("superclass", expr(superclass)),
+                ("methods", Json::Array(methods.iter().map(|method| self.function(method)).collect())),
+            ],
+            Stmt::Expression(value) => vec![("kind", Json::string("Expression")), ("expression", value.accept(self))],
+            Stmt::Function(function) => return self.function(function),
+            Stmt::If(condition, then_branch, else_branch) => vec![
+                ("kind", Json::string("If")),
+                ("condition", condition.accept(self)),
+                ("then", then_branch.accept(self)),
+                ("else", else_branch.as_ref().map(|stmt| stmt.accept(self)).into()),
+            ],
+            Stmt::Print(value) => vec![("kind", Json::string("Print")), ("expression", value.accept(self))],
+            Stmt::Return(_, value) => vec![("kind", Json::string("Return")), ("value", expr(value))],
+            Stmt::Var(name, initializer) => vec![
+                ("kind", Json::string("Var")),
+                ("name", Json::string(name.lexeme)),
+                ("initializer", expr(initializer)),
+            ],
+            Stmt::While(condition, body) => vec![
+                ("kind", Json::string("While")),
+                ("condition", condition.accept(self)),
+                ("body", body.accept(self)),
+            ],
+        };
+        Json::Object(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::Parse;
+    use crate::reporter::Collector;
+    use crate::scanner::Scanner;
+
+    use super::*;
+
+    #[test]
+    fn test_token_fields() {
+        let mut collector = Collector::new();
+        let tokens = Scanner::new("x = \"a\";", &mut collector).scan_all();
+        let json = JsonPrinter.tokens(&tokens).to_string();
+
+        assert!(json.starts_with("{\n  \"version\": 1,\n  \"tokens\": [\n"));
+        assert!(json.contains("\
+    {
+      \"type\": \"STRING\",
+      \"lexeme\": \"\\\"a\\\"\",
+      \"literal\": \"a\",
+      \"line\": 1,
+      \"column\": 5
+    },"));
+    }
+
+    #[test]
+    fn test_expression_node_fields() {
+        let mut collector = Collector::new();
+        let statements = Parse::new(Scanner::new("-x;", &mut collector)).parse().unwrap();
+        let Stmt::Expression(expr) = &statements[0] else { panic!("expected an expression") };
+
+        assert_eq!(JsonPrinter.print(expr), Json::Object(vec![
+            ("kind", Json::string("Unary")),
+            ("operator", Json::string("-")),
+            ("literal", Json::Null),
+            ("span", span(Span { start: 0, end: 2, start_line: 1, start_column: 1, end_line: 1, end_column: 3 })),
+            ("operand", Json::Object(vec![
+                ("kind", Json::string("Variable")),
+                ("operator", Json::Null),
+                ("literal", Json::Null),
+                ("span", span(Span { start: 1, end: 2, start_line: 1, start_column: 2, end_line: 1, end_column: 3 })),
+                ("name", Json::string("x")),
+            ])),
+        ]));
+    }
+
+    #[test]
+    fn test_program() {
+        let mut collector = Collector::new();
+        let source = "var a = 1; fun f(x) { return \"${x}\"; } if (a) print f(a); else while (true) {}";
+        let statements = Parse::new(Scanner::new(source, &mut collector)).parse().unwrap();
+        let Json::Object(fields) = JsonPrinter.program(&statements) else { panic!("expected an object") };
+
+        assert_eq!(fields[0], ("version", Json::Number(1.0)));
+        let Json::Array(statements) = &fields[1].1 else { panic!("expected an array") };
+        let kinds: Vec<_> = statements.iter()
+            .map(|stmt| match stmt {
+                Json::Object(fields) => fields[0].1.clone(),
+                _ => Json::Null,
+            })
+            .collect();
+        assert_eq!(kinds, vec![Json::string("Var"), Json::string("Function"), Json::string("If")]);
+    }
+}
@@ -1,8 +1,10 @@
 use crate::expressions::expr::Expr;
 
 pub mod ast_printer;
+pub mod dot_printer;
+pub mod json_printer;
 pub mod expr;
 
-pub trait Visitor<T> {
-    fn visit_expr(&self, expr: &Expr) -> T;
+pub trait Visitor<'src, T> {
+    fn visit_expr(&self, expr: &Expr<'src>) -> T;
 }
@@ -0,0 +1,708 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::callable::{Callable, LoxFunction, NativeFunction};
+use crate::class::{LoxClass, LoxInstance};
+use crate::environment::Environment;
+use crate::error::RuntimeError;
+use crate::expressions;
+use crate::expressions::expr::{Expr, ExprId};
+use crate::statements;
+use crate::statements::stmt::{Function, Stmt};
+use crate::symbol::Symbol;
+use crate::token::{Token, TokenType};
+use crate::value::Value;
+
+pub struct Interpreter<'src> {
+    globals: Rc<RefCell<Environment<'src>>>,
+    locals: RefCell<HashMap<ExprId, usize>>,
+    environment: RefCell<Rc<RefCell<Environment<'src>>>>,
+    output: RefCell<Box<dyn Write>>,
+}
+
+/// Ways statement execution can leave the normal flow: a runtime error, or a
+/// `return` unwinding to the enclosing call.
+#[derive(Debug)]
+pub enum Unwind<'src> {
+    Error(RuntimeError<'src>),
+    Return(Value<'src>),
+}
+
+impl<'src> From<RuntimeError<'src>> for Unwind<'src> {
+    fn from(error: RuntimeError<'src>) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+impl<'src> Interpreter<'src> {
+    pub fn new() -> Self {
+        Self::with_output(Box::new(io::stdout()))
+    }
+
+    /// Creates an interpreter whose `print` statements write to `output`
+    /// instead of stdout.
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define(Symbol::intern("clock"), Value::NativeFunction(Rc::new(NativeFunction {
+            name: "clock",
+            arity: 0,
+            function: clock,
+        })));
+
+        Interpreter {
+            environment: RefCell::new(Rc::clone(&globals)),
+            globals,
+            locals: RefCell::new(HashMap::new()),
+            output: RefCell::new(output),
+        }
+    }
+
+    pub fn interpret(&self, statements: &[Stmt<'src>]) -> Result<(), RuntimeError<'src>> {
+        for statement in statements {
+            match self.execute(statement) {
+                Ok(()) => {}
+                Err(Unwind::Error(error)) => return Err(error),
+                // A top-level `return` just ends the program.
+                Err(Unwind::Return(_)) => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Every global variable and its value, sorted by name.
+    pub fn globals(&self) -> Vec<(Symbol, Value<'src>)> {
+        let mut globals: Vec<_> = self.globals.borrow().values().map(|(name, value)| (name, value.clone())).collect();
+        globals.sort_by_key(|(name, _)| name.as_str());
+        globals
+    }
+
+    /// Records that the variable expression `id` refers to a local declared
+    /// `depth` scopes out from where it is used.
+    pub fn resolve(&self, id: ExprId, depth: usize) {
+        self.locals.borrow_mut().insert(id, depth);
+    }
+
+    fn look_up_variable(&self, name: &Token<'src>, id: ExprId) -> Result<Value<'src>, RuntimeError<'src>> {
+        match self.locals.borrow().get(&id) {
+            Some(distance) => Environment::get_at(&self.environment.borrow(), *distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn execute(&self, stmt: &Stmt<'src>) -> Result<(), Unwind<'src>> {
+        stmt.accept(self)
+    }
+
+    pub fn execute_block(&self, statements: &[Stmt<'src>], environment: Environment<'src>) -> Result<(), Unwind<'src>> {
+        let previous = self.environment.replace(Rc::new(RefCell::new(environment)));
+
+        let result = statements.iter().try_for_each(|statement| self.execute(statement));
+
+        self.environment.replace(previous);
+        result
+    }
+
+    fn evaluate(&self, expr: &Expr<'src>) -> Result<Value<'src>, RuntimeError<'src>> {
+        expr.accept(self)
+    }
+
+    fn print(&self, value: &Value) {
+        let mut output = self.output.borrow_mut();
+        // A closed stdout is not a Lox runtime error, so the write result is ignored.
+        let _ = writeln!(output, "{}", value);
+    }
+
+    fn class(&self, name: &Token<'src>, superclass: Option<&Expr<'src>>, declarations: &[Rc<Function<'src>>]) -> Result<(), RuntimeError<'src>> {
+        let superclass = match superclass {
+            Some(expr) => match self.evaluate(expr)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    let token = match expr {
+                        Expr::Variable(superclass, _) => superclass,
+                        _ => name,
+                    };
+                    return Err(RuntimeError::new(token, "Superclass must be a class."));
+                }
+            },
+            None => None,
+        };
+
+        self.environment.borrow().borrow_mut().define(name.symbol(), Value::Nil);
+
+        // Methods of a subclass close over an extra scope that binds `super`
+        // to the superclass, so `super` follows the defining class.
+        let mut environment = Rc::clone(&self.environment.borrow());
+        if let Some(superclass) = &superclass {
+            let mut scope = Environment::with_enclosing(environment);
+            scope.define(Symbol::SUPER, Value::Class(Rc::clone(superclass)));
+            environment = Rc::new(RefCell::new(scope));
+        }
+
+        let methods = declarations.iter()
+            .map(|declaration| {
+                let name = declaration.name.symbol();
+                let method = LoxFunction::new(Rc::clone(declaration), Rc::clone(&environment), name == Symbol::INIT);
+                (name, Rc::new(method))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let class = LoxClass::new(name.symbol(), superclass, methods);
+        self.environment.borrow().borrow_mut().assign(name, Value::Class(Rc::new(class)))
+    }
+
+    fn super_method(&self, keyword: &Token<'src>, method: &Token<'src>, id: ExprId) -> Result<Value<'src>, RuntimeError<'src>> {
+        // The resolver rejects `super` outside a subclass, so only code
+        // that was never resolved can get here without a depth.
+        let Some(&distance) = self.locals.borrow().get(&id) else {
+            return Err(RuntimeError::new(keyword, "Can't use 'super' outside of a class."));
+        };
+        let environment = self.environment.borrow();
+        let Value::Class(superclass) = Environment::get_at(&environment, distance, keyword)? else {
+            unreachable!("'super' is always bound to a class");
+        };
+        // `this` is bound in the scope just inside the one holding `super`.
+        let this = Token::word(TokenType::This, "this", Symbol::THIS, keyword.span());
+        let Value::Instance(instance) = Environment::get_at(&environment, distance - 1, &this)? else {
+            unreachable!("'this' is always bound to an instance");
+        };
+
+        match superclass.find_method(method.symbol()) {
+            Some(function) => Ok(Value::Function(Rc::new(function.bind(instance)))),
+            None => Err(RuntimeError::new(method, &format!("Undefined property '{}'.", method.lexeme))),
+        }
+    }
+
+    fn call(&self, callee: &Expr<'src>, paren: &Token<'src>, arguments: &[Expr<'src>]) -> Result<Value<'src>, RuntimeError<'src>> {
+        let callee = self.evaluate(callee)?;
+
+        let arguments = arguments.iter()
+            .map(|argument| self.evaluate(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let function: &dyn Callable<'src> = match &callee {
+            Value::Function(function) => function.as_ref(),
+            Value::NativeFunction(function) => function.as_ref(),
+            Value::Class(class) => class,
+            _ => return Err(RuntimeError::new(paren, "Can only call functions and classes.")),
+        };
+
+        if arguments.len() != function.arity() {
+            let message = format!("Expected {} arguments but got {}.", function.arity(), arguments.len());
+            return Err(RuntimeError::new(paren, &message));
+        }
+
+        function.call(self, arguments)
+    }
+
+    fn unary(&self, op: &Token<'src>, rhs: &Expr<'src>) -> Result<Value<'src>, RuntimeError<'src>> {
+        let right = self.evaluate(rhs)?;
+
+        match op.token_type {
+            TokenType::Minus => Ok(Value::Number(-number_operand(op, &right)?)),
+            TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
+            _ => unreachable!("Invalid unary operator {}", op.lexeme),
+        }
+    }
+
+    fn binary(&self, lhs: &Expr<'src>, op: &Token<'src>, rhs: &Expr<'src>) -> Result<Value<'src>, RuntimeError<'src>> {
+        let left = self.evaluate(lhs)?;
+        let right = self.evaluate(rhs)?;
+
+        match op.token_type {
+            TokenType::Minus => {
+                let (l, r) = number_operands(op, &left, &right)?;
+                Ok(Value::Number(l - r))
+            }
+            TokenType::Slash => {
+                let (l, r) = number_operands(op, &left, &right)?;
+                Ok(Value::Number(l / r))
+            }
+            TokenType::Star => {
+                let (l, r) = number_operands(op, &left, &right)?;
+                Ok(Value::Number(l * r))
+            }
+            TokenType::Plus => match (left, right) {
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
+                (Value::String(l), Value::String(r)) => Ok(Value::String(Rc::from(format!("{l}{r}")))),
+                _ => Err(RuntimeError::new(op, "Operands must be two numbers or two strings.")),
+            },
+            TokenType::Greater => {
+                let (l, r) = number_operands(op, &left, &right)?;
+                Ok(Value::Bool(l > r))
+            }
+            TokenType::GreaterEqual => {
+                let (l, r) = number_operands(op, &left, &right)?;
+                Ok(Value::Bool(l >= r))
+            }
+            TokenType::Less => {
+                let (l, r) = number_operands(op, &left, &right)?;
+                Ok(Value::Bool(l < r))
+            }
+            TokenType::LessEqual => {
+                let (l, r) = number_operands(op, &left, &right)?;
+                Ok(Value::Bool(l <= r))
+            }
+            TokenType::BangEqual => Ok(Value::Bool(left != right)),
+            TokenType::EqualEqual => Ok(Value::Bool(left == right)),
+            _ => unreachable!("Invalid binary operator {}", op.lexeme),
+        }
+    }
+}
+
+fn clock<'src>(_arguments: &[Value<'src>]) -> Value<'src> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    Value::Number(now.as_secs_f64())
+}
+
+fn number_operand<'src>(op: &Token<'src>, operand: &Value<'src>) -> Result<f64, RuntimeError<'src>> {
+    match operand {
+        Value::Number(n) => Ok(*n),
+        _ => Err(RuntimeError::new(op, "Operand must be a number.")),
+    }
+}
+
+fn number_operands<'src>(op: &Token<'src>, left: &Value<'src>, right: &Value<'src>) -> Result<(f64, f64), RuntimeError<'src>> {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => Ok((*l, *r)),
+        _ => Err(RuntimeError::new(op, "Operands must be numbers.")),
+    }
+}
+
+impl Default for Interpreter<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'src> expressions::Visitor<'src, Result<Value<'src>, RuntimeError<'src>>> for Interpreter<'src> {
+    fn visit_expr(&self, expr: &Expr<'src>) -> Result<Value<'src>, RuntimeError<'src>> {
+        match expr {
+            Expr::Assign(name, value, id) => {
+                let value = self.evaluate(value)?;
+                match self.locals.borrow().get(id) {
+                    Some(distance) => Environment::assign_at(&self.environment.borrow(), *distance, name, value.clone()),
+                    None => self.globals.borrow_mut().assign(name, value.clone())?,
+                }
+                Ok(value)
+            }
+            Expr::Binary(lhs, op, rhs) => self.binary(lhs, op, rhs),
+            Expr::Call(callee, paren, arguments) => self.call(callee, paren, arguments),
+            Expr::Get(object, name) => match self.evaluate(object)? {
+                Value::Instance(instance) => LoxInstance::get(&instance, name),
+                _ => Err(RuntimeError::new(name, "Only instances have properties.")),
+            },
+            Expr::Grouping(exp, _) => self.evaluate(exp),
+            Expr::Interpolation(parts, _) => {
+                // Each part is stringified as `print` would show it.
+                let mut text = String::new();
+                for part in parts {
+                    text.push_str(&self.evaluate(part)?.to_string());
+                }
+                Ok(Value::String(Rc::from(text)))
+            }
+            Expr::Literal(literal, _) => Ok(Value::from(literal)),
+            Expr::Logical(lhs, op, rhs) => {
+                let left = self.evaluate(lhs)?;
+
+                // The deciding operand itself is the result, not a coerced bool.
+                let short_circuits = match op.token_type {
+                    TokenType::Or => left.is_truthy(),
+                    _ => !left.is_truthy(),
+                };
+
+                if short_circuits {
+                    Ok(left)
+                } else {
+                    self.evaluate(rhs)
+                }
+            }
+            Expr::Set(object, name, value) => {
+                let Value::Instance(instance) = self.evaluate(object)? else {
+                    return Err(RuntimeError::new(name, "Only instances have fields."));
+                };
+
+                let value = self.evaluate(value)?;
+                instance.borrow_mut().set(name, value.clone());
+                Ok(value)
+            }
+            Expr::Super(keyword, method, id) => self.super_method(keyword, method, *id),
+            Expr::This(keyword, id) => self.look_up_variable(keyword, *id),
+            Expr::Unary(op, rhs) => self.unary(op, rhs),
+            Expr::Variable(name, id) => self.look_up_variable(name, *id),
+        }
+    }
+}
+
+impl<'src> statements::Visitor<'src, Result<(), Unwind<'src>>> for Interpreter<'src> {
+    fn visit_stmt(&self, stmt: &Stmt<'src>) -> Result<(), Unwind<'src>> {
+        match stmt {
+            Stmt::Block(statements) => {
+                let enclosing = Rc::clone(&self.environment.borrow());
+                self.execute_block(statements, Environment::with_enclosing(enclosing))
+            }
+            Stmt::Class(name, superclass, declarations) => {
+                self.class(name, superclass.as_ref(), declarations)?;
+                Ok(())
+            }
+            Stmt::Expression(expr) => {
+                self.evaluate(expr)?;
+                Ok(())
+            }
+            Stmt::Function(declaration) => {
+                let closure = Rc::clone(&self.environment.borrow());
+                let function = LoxFunction::new(Rc::clone(declaration), closure, false);
+                self.environment.borrow().borrow_mut()
+                    .define(declaration.name.symbol(), Value::Function(Rc::new(function)));
+                Ok(())
+            }
+            Stmt::If(condition, then_branch, else_branch) => {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.execute(then_branch)
+                } else if let Some(else_branch) = else_branch {
+                    self.execute(else_branch)
+                } else {
+                    Ok(())
+                }
+            }
+            Stmt::Print(expr) => {
+                let value = self.evaluate(expr)?;
+                self.print(&value);
+                Ok(())
+            }
+            Stmt::Return(_, value) => {
+                let value = match value {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Value::Nil,
+                };
+                Err(Unwind::Return(value))
+            }
+            Stmt::Var(name, initializer) => {
+                let value = match initializer {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Value::Nil,
+                };
+                self.environment.borrow().borrow_mut().define(name.symbol(), value);
+                Ok(())
+            }
+            Stmt::While(condition, body) => {
+                while self.evaluate(condition)?.is_truthy() {
+                    self.execute(body)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::Parse;
+    use crate::reporter::Collector;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+    use crate::test_support::Output;
+
+    use super::*;
+
+    fn parse(source: &str) -> Vec<Stmt<'_>> {
+        let mut collector = Collector::new();
+        Parse::new(Scanner::new(source, &mut collector)).parse().unwrap()
+    }
+
+    fn run(source: &str) -> Result<String, RuntimeError<'_>> {
+        let output = Output::default();
+        let interpreter = Interpreter::with_output(Box::new(output.clone()));
+        let statements = parse(source);
+        let mut collector = Collector::new();
+        Resolver::new(&interpreter, &mut collector).resolve(&statements);
+        assert!(collector.diagnostics().is_empty());
+        interpreter.interpret(&statements)?;
+        Ok(output.contents())
+    }
+
+    fn eval(source: &str) -> Result<Value<'static>, RuntimeError<'static>> {
+        // Leaked so the result can borrow from it after this returns.
+        match &parse(format!("{source};").leak())[0] {
+            Stmt::Expression(expr) => Interpreter::new().evaluate(expr),
+            stmt => panic!("Expected expression statement, got {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(Value::Number(7.0)));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(Value::Number(9.0)));
+        assert_eq!(eval("10 / 4 - -1"), Ok(Value::Number(3.5)));
+    }
+
+    #[test]
+    fn test_comparison_and_equality() {
+        assert_eq!(eval("1 < 2"), Ok(Value::Bool(true)));
+        assert_eq!(eval("2 <= 1"), Ok(Value::Bool(false)));
+        assert_eq!(eval("1 == 1"), Ok(Value::Bool(true)));
+        assert_eq!(eval("nil == false"), Ok(Value::Bool(false)));
+        assert_eq!(eval("\"a\" != \"b\""), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_truthiness() {
+        assert_eq!(eval("!nil"), Ok(Value::Bool(true)));
+        assert_eq!(eval("!0"), Ok(Value::Bool(false)));
+        assert_eq!(eval("!!\"\""), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        assert_eq!(eval("\"lo\" + \"x\""), Ok(Value::String(Rc::from("lox"))));
+        assert_eq!(eval("\"lo\" + \"x\" == \"l\" + \"ox\""), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        assert_eq!(eval(r#""${1 + 2} is ${"three"}, not ${nil} or ${1 < 0}""#),
+                   Ok(Value::String(Rc::from("3 is three, not nil or false"))));
+        assert_eq!(run("class A {} var a = A(); print \"<${a}> <${A}> ${\"${2.5}\"}\";").unwrap(),
+                   "<A instance> <A> 2.5\n");
+    }
+
+    #[test]
+    fn test_unicode_names() {
+        assert_eq!(run("var 이름 = \"lox\"; var caf\u{e9} = 1; print 이름 + \"!\"; print cafe\u{301} + 1;").unwrap(),
+                   "lox!\n2\n");
+    }
+
+    #[test]
+    fn test_operand_type_error() {
+        let error = eval("1 +\n\"a\" * 2").unwrap_err();
+
+        assert_eq!(error.message, "Operands must be numbers.");
+        assert_eq!(error.token.line(), 2);
+
+        let error = eval("-\"a\"").unwrap_err();
+        assert_eq!(error.message, "Operand must be a number.");
+    }
+
+    #[test]
+    fn test_block_scoping() {
+        let output = run("var a = 1; { var a = 2; print a; } print a;").unwrap();
+
+        assert_eq!(output, "2\n1\n");
+    }
+
+    #[test]
+    fn test_assignment() {
+        let output = run("var a; print a; { a = \"outer\"; var b = a = 3; print b; } print a;").unwrap();
+
+        assert_eq!(output, "nil\n3\n3\n");
+    }
+
+    #[test]
+    fn test_if_else() {
+        let output = run("if (1 < 2) print \"then\"; else print \"else\";\n\
+                          if (nil) print \"then\"; else print \"else\";\n\
+                          if (false) print \"skipped\";").unwrap();
+
+        assert_eq!(output, "then\nelse\n");
+    }
+
+    #[test]
+    fn test_dangling_else() {
+        let output = run("if (true) if (false) print 1; else print 2;").unwrap();
+
+        assert_eq!(output, "2\n");
+    }
+
+    #[test]
+    fn test_logical_operators_return_operand() {
+        assert_eq!(eval("nil or \"yes\""), Ok(Value::String(Rc::from("yes"))));
+        assert_eq!(eval("1 or 2"), Ok(Value::Number(1.0)));
+        assert_eq!(eval("nil and 2"), Ok(Value::Nil));
+        assert_eq!(eval("1 and 2"), Ok(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn test_logical_operators_short_circuit() {
+        let output = run("var a = 0; true or (a = 1); false and (a = 2); print a;").unwrap();
+
+        assert_eq!(output, "0\n");
+    }
+
+    #[test]
+    fn test_while() {
+        let output = run("var i = 0; while (i < 3) { print i; i = i + 1; }").unwrap();
+
+        assert_eq!(output, "0\n1\n2\n");
+    }
+
+    #[test]
+    fn test_for() {
+        let output = run("var a = 0; var temp;\n\
+                          for (var b = 1; a < 50; b = temp + b) { print a; temp = a; a = b; }").unwrap();
+
+        assert_eq!(output, "0\n1\n1\n2\n3\n5\n8\n13\n21\n34\n");
+    }
+
+    #[test]
+    fn test_for_initializer_is_scoped_to_loop() {
+        let error = run("for (var i = 0; i < 1; i = i + 1) {}\nprint i;").unwrap_err();
+
+        assert_eq!(error.message, "Undefined variable 'i'.");
+    }
+
+    #[test]
+    fn test_function_call() {
+        let output = run("fun add(a, b) { return a + b; }\n\
+                          fun hello(name) { print \"hello \" + name; }\n\
+                          print add(1, 2); print hello(\"lox\"); print add;").unwrap();
+
+        assert_eq!(output, "3\nhello lox\nnil\n<fn add>\n");
+    }
+
+    #[test]
+    fn test_recursion_and_early_return() {
+        let output = run("fun fib(n) { if (n < 2) return n; return fib(n - 2) + fib(n - 1); }\n\
+                          print fib(10);").unwrap();
+
+        assert_eq!(output, "55\n");
+    }
+
+    #[test]
+    fn test_closure_counter() {
+        let output = run("fun makeCounter() { var i = 0; fun count() { i = i + 1; return i; } return count; }\n\
+                          var counter = makeCounter(); counter(); print counter();\n\
+                          var other = makeCounter(); print other();").unwrap();
+
+        assert_eq!(output, "2\n1\n");
+    }
+
+    #[test]
+    fn test_arity_mismatch() {
+        let error = run("fun f(a, b) {}\nf(1);").unwrap_err();
+
+        assert_eq!(error.message, "Expected 2 arguments but got 1.");
+        assert_eq!(error.token.line(), 2);
+    }
+
+    #[test]
+    fn test_call_non_callable() {
+        let error = run("\"not a function\"();").unwrap_err();
+
+        assert_eq!(error.message, "Can only call functions and classes.");
+    }
+
+    #[test]
+    fn test_closure_binds_to_declaration_scope() {
+        let output = run("var a = \"global\";\n\
+                          { fun showA() { print a; } showA(); var a = \"block\"; showA(); print a; }").unwrap();
+
+        assert_eq!(output, "global\nglobal\nblock\n");
+    }
+
+    #[test]
+    fn test_native_clock() {
+        let output = run("var start = clock(); print clock() >= start; print clock;").unwrap();
+
+        assert_eq!(output, "true\n<native fn>\n");
+    }
+
+    #[test]
+    fn test_class_and_instance_to_string() {
+        let output = run("class Foo {} print Foo; print Foo();").unwrap();
+
+        assert_eq!(output, "Foo\nFoo instance\n");
+    }
+
+    #[test]
+    fn test_fields_and_methods() {
+        let output = run("class Bacon { eat() { print \"Crunch \" + this.kind; } }\n\
+                          var bacon = Bacon(); bacon.kind = \"crispy\"; bacon.eat();").unwrap();
+
+        assert_eq!(output, "Crunch crispy\n");
+    }
+
+    #[test]
+    fn test_bound_method_remembers_this() {
+        let output = run("class Person { sayName() { print this.name; } }\n\
+                          var jane = Person(); jane.name = \"Jane\";\n\
+                          var bill = Person(); bill.name = \"Bill\";\n\
+                          bill.sayName = jane.sayName; bill.sayName();").unwrap();
+
+        assert_eq!(output, "Jane\n");
+    }
+
+    #[test]
+    fn test_initializer() {
+        let output = run("class Point { init(x, y) { this.x = x; this.y = y; return; } }\n\
+                          var p = Point(1, 2); print p.x + p.y; print p.init(3, 4); print p.x;").unwrap();
+
+        assert_eq!(output, "3\nPoint instance\n3\n");
+
+        let error = run("class Point { init(x) {} }\nPoint();").unwrap_err();
+        assert_eq!(error.message, "Expected 1 arguments but got 0.");
+    }
+
+    #[test]
+    fn test_property_errors() {
+        let error = run("class Foo {}\nFoo().bar;").unwrap_err();
+        assert_eq!(error.message, "Undefined property 'bar'.");
+
+        let error = run("var a = 1; a.b;").unwrap_err();
+        assert_eq!(error.message, "Only instances have properties.");
+
+        let error = run("var a = 1; a.b = 2;").unwrap_err();
+        assert_eq!(error.message, "Only instances have fields.");
+    }
+
+    #[test]
+    fn test_inherited_methods() {
+        let output = run("class Doughnut { cook() { print \"Fry until golden brown.\"; } }\n\
+                          class BostonCream < Doughnut {}\n\
+                          BostonCream().cook();").unwrap();
+
+        assert_eq!(output, "Fry until golden brown.\n");
+    }
+
+    #[test]
+    fn test_super_call() {
+        let output = run("class A { init(x) { this.x = x; } describe() { return \"A\" + this.x; } }\n\
+                          class B < A { init() { super.init(\"!\"); } describe() { return \"B\" + super.describe(); } }\n\
+                          print B().describe();").unwrap();
+
+        assert_eq!(output, "BA!\n");
+    }
+
+    #[test]
+    fn test_super_binds_to_defining_class() {
+        let output = run("class A { method() { print \"A method\"; } }\n\
+                          class B < A { method() { print \"B method\"; } test() { super.method(); } }\n\
+                          class C < B {}\n\
+                          C().test();").unwrap();
+
+        assert_eq!(output, "A method\n");
+    }
+
+    #[test]
+    fn test_inheritance_errors() {
+        let error = run("var NotAClass = \"nope\";\nclass Sub < NotAClass {}").unwrap_err();
+        assert_eq!(error.message, "Superclass must be a class.");
+        assert_eq!(error.token.line(), 2);
+
+        let error = run("class A {} class B < A { f() { return super.missing; } }\nB().f();").unwrap_err();
+        assert_eq!(error.message, "Undefined property 'missing'.");
+
+        // Without the resolver there is no depth to find `super` at.
+        assert_eq!(eval("super.method").unwrap_err().message, "Can't use 'super' outside of a class.");
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let error = run("{ var a = 1; }\nprint a;").unwrap_err();
+
+        assert_eq!(error.message, "Undefined variable 'a'.");
+        assert_eq!(error.token.line(), 2);
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::{self, Write};
+
+/// A JSON value, built by the exporters and written out with `Display`.
+/// Object fields keep the order they were added in, so output is stable
+/// from one run to the next and can be diffed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    pub fn string(text: &str) -> Json {
+        Json::String(text.to_string())
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            // JSON has no infinities or NaN.
+            Json::Number(n) if !n.is_finite() => f.write_str("null"),
+            Json::Number(n) => write!(f, "{n}"),
+            Json::String(text) => write_string(f, text),
+            Json::Array(items) if items.is_empty() => f.write_str("[]"),
+            Json::Array(items) => {
+                f.write_str("[\n")?;
+                for (i, item) in items.iter().enumerate() {
+                    write_indent(f, indent + 1)?;
+                    item.write(f, indent + 1)?;
+                    f.write_str(if i + 1 < items.len() { ",\n" } else { "\n" })?;
+                }
+                write_indent(f, indent)?;
+                f.write_char(']')
+            }
+            Json::Object(fields) if fields.is_empty() => f.write_str("{}"),
+            Json::Object(fields) => {
+                f.write_str("{\n")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    write_indent(f, indent + 1)?;
+                    write_string(f, name)?;
+                    f.write_str(": ")?;
+                    value.write(f, indent + 1)?;
+                    f.write_str(if i + 1 < fields.len() { ",\n" } else { "\n" })?;
+                }
+                write_indent(f, indent)?;
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl From<Option<Json>> for Json {
+    fn from(value: Option<Json>) -> Self {
+        value.unwrap_or(Json::Null)
+    }
+}
+
+fn write_indent(f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+    for _ in 0..indent {
+        f.write_str("  ")?;
+    }
+    Ok(())
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in text.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(Json::Null.to_string(), "null");
+        assert_eq!(Json::Bool(true).to_string(), "true");
+        assert_eq!(Json::Number(1.0).to_string(), "1");
+        assert_eq!(Json::Number(-2.5).to_string(), "-2.5");
+        assert_eq!(Json::Number(f64::INFINITY).to_string(), "null");
+        assert_eq!(Json::string("a\"b\\c\n\u{1}é").to_string(), r#""a\"b\\c\n\u0001é""#);
+    }
+
+    #[test]
+    fn test_nesting_keeps_field_order() {
+        let json = Json::Object(vec![
+            ("zeta", Json::Number(1.0)),
+            ("alpha", Json::Array(vec![Json::Null, Json::Object(vec![("b", Json::Bool(false))])])),
+            ("empty", Json::Array(vec![])),
+        ]);
+
+        assert_eq!(json.to_string(), "\
+{
+  \"zeta\": 1,
+  \"alpha\": [
+    null,
+    {
+      \"b\": false
+    }
+  ],
+  \"empty\": []
+}");
+    }
+}
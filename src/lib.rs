@@ -0,0 +1,20 @@
+pub mod token;
+pub mod span;
+pub mod scanner;
+pub mod error;
+pub mod diagnostic;
+pub mod expressions;
+pub mod parser;
+pub mod value;
+pub mod interpreter;
+pub mod statements;
+pub mod environment;
+pub mod bytecode;
+pub mod callable;
+pub mod class;
+pub mod reporter;
+pub mod resolver;
+pub mod symbol;
+pub mod json;
+#[cfg(test)]
+mod test_support;
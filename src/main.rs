@@ -1,54 +1,370 @@
-use std::{env, io};
-use std::fs::File;
-use std::io::Write;
-use crate::scanner::Scanner;
-
-mod token;
-mod scanner;
-mod error;
-mod expressions;
-mod parser;
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    if args.len() > 2 {
-        eprintln!("Usage: {} [script]", args[0]);
-        std::process::exit(64);
-    } else if args.len() == 2 {
-        run_file(&args[1]).unwrap();
-    } else {
-        run_prompt().unwrap();
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use lox_rs::bytecode::compiler::Compiler;
+use lox_rs::bytecode::object::ObjRef;
+use lox_rs::bytecode::value::Value;
+use lox_rs::bytecode::vm::VM;
+use lox_rs::error::{runtime_error, vm_runtime_error};
+use lox_rs::expressions::ast_printer::AstPrinter;
+use lox_rs::expressions::dot_printer::DotPrinter;
+use lox_rs::expressions::json_printer::JsonPrinter;
+use lox_rs::interpreter::Interpreter;
+use lox_rs::parser::Parse;
+use lox_rs::reporter::{Reporter, StderrReporter};
+use lox_rs::resolver::Resolver;
+use lox_rs::scanner::Scanner;
+use lox_rs::statements::stmt::Stmt;
+
+mod repl;
+
+// Exit codes from sysexits.h, as used by jlox.
+const EX_USAGE: u8 = 64;
+const EX_DATAERR: u8 = 65;
+const EX_NOINPUT: u8 = 66;
+const EX_SOFTWARE: u8 = 70;
+const EX_IOERR: u8 = 74;
+
+const USAGE: &str = "\
+Usage: lox [options] [<command> <script>]
+       lox --emit=<format> <script>
+
+Commands:
+    run       Run a script
+    tokens    Print the tokens of a script
+    ast       Print the syntax tree of a script
+    check     Parse and resolve a script without running it
+
+Options:
+    --backend=<tree|vm>    Execute with the tree-walking interpreter (default)
+                           or the bytecode VM
+    --gc-stress            Collect garbage on every VM allocation
+    --gc-log               Print how much each VM collection frees
+    --emit=<format>        Print a script in a machine-readable format instead
+                           of running it: tokens-json, ast-json, or ast-dot
+                           for a Graphviz graph of the syntax tree
+
+With no command, lox starts an interactive prompt. Type :help there for the
+commands it understands.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Run,
+    Tokens,
+    Ast,
+    Check,
+}
+
+impl Command {
+    fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "run" => Some(Command::Run),
+            "tokens" => Some(Command::Tokens),
+            "ast" => Some(Command::Ast),
+            "check" => Some(Command::Check),
+            _ => None,
+        }
     }
 }
 
-fn run_file(filename: &str) -> io::Result<()> {
-    let file = File::open(filename)?;
-    let source = io::read_to_string(file)?;
-    run(source)
+/// A machine-readable rendering of a script, for `--emit`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Emit {
+    TokensJson,
+    AstJson,
+    AstDot,
 }
 
-fn run_prompt() -> io::Result<()> {
-    loop {
-        print!("> ");
-        io::stdout().flush()?;
+impl Emit {
+    fn from_name(name: &str) -> Option<Emit> {
+        match name {
+            "tokens-json" => Some(Emit::TokensJson),
+            "ast-json" => Some(Emit::AstJson),
+            "ast-dot" => Some(Emit::AstDot),
+            _ => None,
+        }
+    }
+}
 
-        let mut line = String::new();
-        io::stdin().read_line(&mut line)?;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Backend {
+    Tree,
+    Vm,
+}
 
-        if line.len() == 0 {
-            break;
+impl Backend {
+    fn from_name(name: &str) -> Option<Backend> {
+        match name {
+            "tree" => Some(Backend::Tree),
+            "vm" => Some(Backend::Vm),
+            _ => None,
         }
+    }
+}
+
+/// The state that lives as long as a session: a whole script, or every line
+/// typed at the prompt. The tree-walker's functions point into the source
+/// they were declared in, so that source must outlive the session.
+enum Engine<'src> {
+    Tree(Interpreter<'src>),
+    Vm(VM),
+}
 
-        run(line)?;
+impl<'src> Engine<'src> {
+    fn new(options: &Options) -> Self {
+        match options.backend {
+            Backend::Tree => Engine::Tree(Interpreter::new()),
+            Backend::Vm => {
+                let mut vm = VM::new();
+                vm.set_gc_stress(options.gc_stress);
+                vm.set_gc_log(options.gc_log);
+                Engine::Vm(vm)
+            }
+        }
+    }
+
+    fn run(&mut self, source: &'src str, repl: bool) -> Result<(), Failure> {
+        match self {
+            Engine::Tree(interpreter) => run(interpreter, source, repl),
+            Engine::Vm(vm) => run_bytecode(vm, source, repl),
+        }
+    }
+
+    /// Every global variable and its value as `print` would show it, sorted
+    /// by name.
+    fn globals(&self) -> Vec<(String, String)> {
+        let mut globals: Vec<_> = match self {
+            Engine::Tree(interpreter) => interpreter.globals().into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            Engine::Vm(vm) => vm.globals()
+                .map(|(name, value)| (vm.heap().display(Value::Obj(name)).to_string(), vm.heap().display(value).to_string()))
+                .collect(),
+        };
+        globals.sort();
+        globals
     }
-    Ok(())
 }
 
-fn run(source: String) -> io::Result<()> {
-    let scanner = Scanner::new(&source);
-    for token in scanner.tokens.iter() {
-        println!("{}", token);
+/// Settings from the command line that apply whatever the command.
+#[derive(Debug, Clone, Copy)]
+struct Options {
+    backend: Backend,
+    gc_stress: bool,
+    gc_log: bool,
+    emit: Option<Emit>,
+}
+
+/// Why running some source failed, which decides the process exit code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Failure {
+    Compile,
+    Runtime,
+    Io,
+}
+
+impl Failure {
+    fn exit_code(self) -> u8 {
+        match self {
+            Failure::Compile => EX_DATAERR,
+            Failure::Runtime => EX_SOFTWARE,
+            Failure::Io => EX_IOERR,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut options = Options {
+        backend: Backend::Tree,
+        gc_stress: false,
+        gc_log: false,
+        emit: None,
+    };
+    let mut args = vec![];
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--gc-stress" => options.gc_stress = true,
+            "--gc-log" => options.gc_log = true,
+            _ => {
+                if let Some(name) = arg.strip_prefix("--backend=") {
+                    match Backend::from_name(name) {
+                        Some(selected) => options.backend = selected,
+                        None => return ExitCode::from(usage()),
+                    }
+                } else if let Some(name) = arg.strip_prefix("--emit=") {
+                    match Emit::from_name(name) {
+                        Some(selected) => options.emit = Some(selected),
+                        None => return ExitCode::from(usage()),
+                    }
+                } else if arg.starts_with("--") {
+                    return ExitCode::from(usage());
+                } else {
+                    args.push(arg);
+                }
+            }
+        }
     }
-    Ok(())
+
+    let code = match (options.emit, args.as_slice()) {
+        (Some(emit), [path]) => emit_file(emit, path),
+        (Some(_), _) => usage(),
+        (None, []) => repl::run_prompt(&options),
+        (None, [command, path]) => match Command::from_name(command) {
+            Some(command) => run_file(command, &options, path),
+            None => usage(),
+        },
+        _ => usage(),
+    };
+    ExitCode::from(code)
+}
+
+fn usage() -> u8 {
+    eprintln!("{USAGE}");
+    EX_USAGE
+}
+
+fn read_source(path: &str) -> Result<String, u8> {
+    fs::read_to_string(path).map_err(|error| {
+        eprintln!("Could not read '{path}': {error}");
+        EX_NOINPUT
+    })
+}
+
+fn run_file(command: Command, options: &Options, path: &str) -> u8 {
+    let source = match read_source(path) {
+        Ok(source) => source,
+        Err(code) => return code,
+    };
+
+    let result = match command {
+        Command::Run => Engine::new(options).run(&source, false),
+        Command::Tokens => print_tokens(&source),
+        Command::Ast => print_ast(&source, false),
+        Command::Check => match options.backend {
+            Backend::Tree => check(&source),
+            Backend::Vm => compile_bytecode(&source, &mut VM::new(), false).map(|_| ()),
+        },
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(failure) => failure.exit_code(),
+    }
+}
+
+fn emit_file(emit: Emit, path: &str) -> u8 {
+    let source = match read_source(path) {
+        Ok(source) => source,
+        Err(code) => return code,
+    };
+
+    let mut reporter = StderrReporter::new(&source);
+    let output = match emit {
+        Emit::TokensJson => {
+            let tokens = Scanner::new(&source, &mut reporter).scan_all();
+            if reporter.has_errors() {
+                return Failure::Compile.exit_code();
+            }
+            JsonPrinter.tokens(&tokens).to_string()
+        }
+        Emit::AstJson => match Parse::new(Scanner::new(&source, &mut reporter)).parse() {
+            Some(statements) => JsonPrinter.program(&statements).to_string(),
+            None => return Failure::Compile.exit_code(),
+        },
+        Emit::AstDot => match Parse::new(Scanner::new(&source, &mut reporter)).parse() {
+            Some(statements) => DotPrinter::print_program(&statements),
+            None => return Failure::Compile.exit_code(),
+        },
+    };
+    match write_stdout(output + "\n") {
+        Ok(()) => 0,
+        Err(failure) => failure.exit_code(),
+    }
+}
+
+fn print_tokens(source: &str) -> Result<(), Failure> {
+    let mut reporter = StderrReporter::new(source);
+    let tokens = Scanner::new(source, &mut reporter).scan_all();
+    if reporter.has_errors() {
+        return Err(Failure::Compile);
+    }
+
+    write_stdout(tokens.iter().map(|token| format!("{token}\n")).collect())
+}
+
+fn print_ast(source: &str, repl: bool) -> Result<(), Failure> {
+    let mut reporter = StderrReporter::new(source);
+    let scanner = Scanner::new(source, &mut reporter);
+    let mut parser = if repl { Parse::repl(scanner) } else { Parse::new(scanner) };
+    let statements = parser.parse().ok_or(Failure::Compile)?;
+
+    write_stdout(statements.iter().map(|statement| format!("{}\n", AstPrinter.print_stmt(statement))).collect())
+}
+
+/// Writes `output` to stdout. A reader that stops early, like `head`, is not
+/// an error worth reporting.
+fn write_stdout(output: String) -> Result<(), Failure> {
+    match io::stdout().write_all(output.as_bytes()) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        Err(_) => Err(Failure::Io),
+    }
+}
+
+fn check(source: &str) -> Result<(), Failure> {
+    compile(&Interpreter::new(), source, false).map(|_| ())
+}
+
+fn run<'src>(interpreter: &Interpreter<'src>, source: &'src str, repl: bool) -> Result<(), Failure> {
+    let statements = compile(interpreter, source, repl)?;
+
+    interpreter.interpret(&statements).map_err(|error| {
+        runtime_error(&error);
+        Failure::Runtime
+    })
+}
+
+/// Compiles and runs `source` on `vm`. Compiled functions keep copies of the
+/// names and strings they use, so the source need not outlive the call.
+fn run_bytecode(vm: &mut VM, source: &str, repl: bool) -> Result<(), Failure> {
+    let function = compile_bytecode(source, vm, repl)?;
+    vm.interpret(function).map_err(|error| {
+        vm_runtime_error(&error);
+        Failure::Runtime
+    })
+}
+
+/// Scans, parses and resolves `source`, reporting any errors to stderr.
+fn compile<'src>(interpreter: &Interpreter<'src>, source: &'src str, repl: bool) -> Result<Vec<Stmt<'src>>, Failure> {
+    let mut reporter = StderrReporter::new(source);
+    let scanner = Scanner::new(source, &mut reporter);
+
+    let mut parser = if repl {
+        Parse::repl(scanner)
+    } else {
+        Parse::new(scanner)
+    };
+    let statements = parser.parse().ok_or(Failure::Compile)?;
+
+    Resolver::new(interpreter, &mut reporter).resolve(&statements);
+    if reporter.has_errors() {
+        return Err(Failure::Compile);
+    }
+    Ok(statements)
+}
+
+/// Scans and compiles `source` to a function for `vm` to run, reporting any
+/// errors to stderr.
+fn compile_bytecode(source: &str, vm: &mut VM, repl: bool) -> Result<ObjRef, Failure> {
+    let mut reporter = StderrReporter::new(source);
+    let scanner = Scanner::new(source, &mut reporter);
+
+    let compiler = if repl {
+        Compiler::repl(scanner, vm)
+    } else {
+        Compiler::new(scanner, vm)
+    };
+    compiler.compile().ok_or(Failure::Compile)
 }
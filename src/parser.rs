@@ -1,105 +1,521 @@
-use std::mem;
-use crate::expressions::expr::Expr;
+use std::rc::Rc;
+
+use crate::error::ParseError;
+use crate::expressions::expr::{Expr, ExprId};
+use crate::scanner::{Scanner, TokenStream};
+use crate::statements::stmt::{Function, Stmt};
 use crate::token::{Token, TokenLiteral, TokenType};
 
-pub struct Parse {
-    tokens: Vec<Token>,
-    current: usize,
+type ParseResult<'src, T> = Result<T, ParseError<'src>>;
+
+const MAX_ARGUMENTS: usize = 255;
+
+/// Parses tokens as the scanner produces them, so the whole token list is
+/// never held in memory.
+pub struct Parse<'src, 'r> {
+    tokens: TokenStream<'src, 'r>,
+    repl: bool,
+    had_error: bool,
 }
 
-impl Parse {
-    fn new(tokens: Vec<Token>) -> Self {
+impl<'src, 'r> Parse<'src, 'r> {
+    pub fn new(scanner: Scanner<'src, 'r>) -> Self {
+        Self {
+            tokens: TokenStream::new(scanner),
+            repl: false,
+            had_error: false,
+        }
+    }
+
+    /// A parser for interactive input, where a trailing expression without a
+    /// semicolon is printed instead of being rejected.
+    pub fn repl(scanner: Scanner<'src, 'r>) -> Self {
         Self {
-            tokens,
-            current: 0
+            repl: true,
+            ..Self::new(scanner)
+        }
+    }
+
+    /// Parses the whole program, reporting every scan and syntax error found
+    /// along the way. Returns `None` if there were any.
+    pub fn parse(&mut self) -> Option<Vec<Stmt<'src>>> {
+        let mut statements = vec![];
+
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(_) => self.synchronize(),
+            }
+        }
+
+        if self.had_error || self.tokens.had_error() {
+            None
+        } else {
+            Some(statements)
+        }
+    }
+
+    fn declaration(&mut self) -> ParseResult<'src, Stmt<'src>> {
+        if self.match_tokens(vec![TokenType::Class]) {
+            return self.class_declaration();
+        }
+
+        if self.match_tokens(vec![TokenType::Fun]) {
+            return Ok(Stmt::Function(Rc::new(self.function("function")?)));
+        }
+
+        if self.match_tokens(vec![TokenType::Var]) {
+            return self.var_declaration();
+        }
+        self.statement()
+    }
+
+    fn class_declaration(&mut self) -> ParseResult<'src, Stmt<'src>> {
+        let name = self.consume(TokenType::Identifier, "Expect class name.")?.clone();
+
+        let superclass = if self.match_tokens(vec![TokenType::Less]) {
+            let superclass = self.consume(TokenType::Identifier, "Expect superclass name.")?.clone();
+            Some(Expr::Variable(superclass, ExprId::unique()))
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = vec![];
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(Rc::new(self.function("method")?));
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+        Ok(Stmt::Class(name, superclass, methods))
+    }
+
+    fn function(&mut self, kind: &str) -> ParseResult<'src, Function<'src>> {
+        let name = self.consume(TokenType::Identifier, &format!("Expect {kind} name."))?.clone();
+        self.consume(TokenType::LeftParen, &format!("Expect '(' after {kind} name."))?;
+
+        let mut params = vec![];
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    self.error(self.peek().clone(), "Can't have more than 255 parameters.");
+                }
+
+                params.push(self.consume(TokenType::Identifier, "Expect parameter name.")?.clone());
+
+                if !self.match_tokens(vec![TokenType::Comma]) {
+                    break;
+                }
+            }
         }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+
+        self.consume(TokenType::LeftBrace, &format!("Expect '{{' before {kind} body."))?;
+        let body = self.block()?;
+
+        Ok(Function { name, params, body })
     }
 
-    fn expression(&mut self) -> Expr {
-        self.equality()
+    fn var_declaration(&mut self) -> ParseResult<'src, Stmt<'src>> {
+        let name = self.consume(TokenType::Identifier, "Expect variable name.")?.clone();
+
+        let initializer = if self.match_tokens(vec![TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after variable declaration.")?;
+        Ok(Stmt::Var(name, initializer))
     }
 
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
+    fn statement(&mut self) -> ParseResult<'src, Stmt<'src>> {
+        if self.match_tokens(vec![TokenType::For]) {
+            return self.for_statement();
+        }
+
+        if self.match_tokens(vec![TokenType::If]) {
+            return self.if_statement();
+        }
+
+        if self.match_tokens(vec![TokenType::Print]) {
+            return self.print_statement();
+        }
+
+        if self.match_tokens(vec![TokenType::Return]) {
+            return self.return_statement();
+        }
+
+        if self.match_tokens(vec![TokenType::While]) {
+            return self.while_statement();
+        }
+
+        if self.match_tokens(vec![TokenType::LeftBrace]) {
+            return Ok(Stmt::Block(self.block()?));
+        }
+
+        self.expression_statement()
+    }
+
+    /// There is no `for` node: the loop is desugared into its `while` equivalent,
+    /// wrapped in a block so the initializer's variable stays scoped to the loop.
+    fn for_statement(&mut self) -> ParseResult<'src, Stmt<'src>> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.match_tokens(vec![TokenType::Semicolon]) {
+            None
+        } else if self.match_tokens(vec![TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(&TokenType::Semicolon) {
+            Expr::Literal(TokenLiteral::True, self.peek().span())
+        } else {
+            self.expression()?
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let increment = if self.check(&TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let mut body = self.statement()?;
+
+        if let Some(increment) = increment {
+            body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
+        }
+
+        body = Stmt::While(condition, Box::new(body));
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> ParseResult<'src, Stmt<'src>> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = self.statement()?;
+        // Eagerly taking the `else` binds it to the nearest `if`.
+        let else_branch = if self.match_tokens(vec![TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If(condition, Box::new(then_branch), else_branch))
+    }
+
+    fn return_statement(&mut self) -> ParseResult<'src, Stmt<'src>> {
+        let keyword = self.previous().clone();
+
+        let value = if self.check(&TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+        Ok(Stmt::Return(keyword, value))
+    }
+
+    fn while_statement(&mut self) -> ParseResult<'src, Stmt<'src>> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = self.statement()?;
+
+        Ok(Stmt::While(condition, Box::new(body)))
+    }
+
+    fn print_statement(&mut self) -> ParseResult<'src, Stmt<'src>> {
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(Stmt::Print(value))
+    }
+
+    fn block(&mut self) -> ParseResult<'src, Vec<Stmt<'src>>> {
+        let mut statements = vec![];
+
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    fn expression_statement(&mut self) -> ParseResult<'src, Stmt<'src>> {
+        let expr = self.expression()?;
+
+        if self.repl && self.is_at_end() {
+            return Ok(Stmt::Print(expr));
+        }
+
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    fn expression(&mut self) -> ParseResult<'src, Expr<'src>> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> ParseResult<'src, Expr<'src>> {
+        let expr = self.or()?;
+
+        if self.match_tokens(vec![TokenType::Equal]) {
+            let equals = self.previous().clone();
+            let value = self.assignment()?;
+
+            match expr {
+                Expr::Variable(name, _) => return Ok(Expr::Assign(name, Box::new(value), ExprId::unique())),
+                Expr::Get(object, name) => return Ok(Expr::Set(object, name, Box::new(value))),
+                _ => {}
+            }
+
+            // Report without unwinding: the parser is not confused, the target just isn't assignable.
+            self.error(equals, "Invalid assignment target.");
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> ParseResult<'src, Expr<'src>> {
+        let mut expr = self.and()?;
+
+        while self.match_tokens(vec![TokenType::Or]) {
+            let operator = self.previous().clone();
+            let right = self.and()?;
+            expr = Expr::Logical(Box::new(expr), operator, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> ParseResult<'src, Expr<'src>> {
+        let mut expr = self.equality()?;
+
+        while self.match_tokens(vec![TokenType::And]) {
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+            expr = Expr::Logical(Box::new(expr), operator, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> ParseResult<'src, Expr<'src>> {
+        let mut expr = self.comparison()?;
 
         while self.match_tokens(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
             let operator = self.previous().clone();
-            let right = self.comparison();
+            let right = self.comparison()?;
             expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
         }
-        expr
+        Ok(expr)
     }
 
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
+    fn comparison(&mut self) -> ParseResult<'src, Expr<'src>> {
+        let mut expr = self.term()?;
 
         while self.match_tokens(vec![TokenType::Greater, TokenType::GreaterEqual, TokenType::Less, TokenType::LessEqual]) {
             let operator = self.previous().clone();
-            let right = self.term();
+            let right = self.term()?;
             expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
         }
-        expr
+        Ok(expr)
     }
 
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
+    fn term(&mut self) -> ParseResult<'src, Expr<'src>> {
+        let mut expr = self.factor()?;
 
         while self.match_tokens(vec![TokenType::Minus, TokenType::Plus]) {
             let operator = self.previous().clone();
-            let right = self.factor();
+            let right = self.factor()?;
             expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
         }
-        expr
+        Ok(expr)
     }
 
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
+    fn factor(&mut self) -> ParseResult<'src, Expr<'src>> {
+        let mut expr = self.unary()?;
 
         while self.match_tokens(vec![TokenType::Slash, TokenType::Star]) {
             let operator = self.previous().clone();
-            let right = self.unary();
+            let right = self.unary()?;
             expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
         }
-        expr
+        Ok(expr)
     }
 
-    fn unary(&mut self) -> Expr {
+    fn unary(&mut self) -> ParseResult<'src, Expr<'src>> {
         if self.match_tokens(vec![TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous().clone();
-            let right = self.unary();
-            return Expr::Unary(operator, Box::new(right));
+            let right = self.unary()?;
+            return Ok(Expr::Unary(operator, Box::new(right)));
         }
-        return self.primary();
+        self.call()
     }
 
-    fn primary(&mut self) -> Expr {
+    fn call(&mut self) -> ParseResult<'src, Expr<'src>> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_tokens(vec![TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_tokens(vec![TokenType::Dot]) {
+                let name = self.consume(TokenType::Identifier, "Expect property name after '.'.")?.clone();
+                expr = Expr::Get(Box::new(expr), name);
+            } else {
+                break;
+            }
+        }
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr<'src>) -> ParseResult<'src, Expr<'src>> {
+        let mut arguments = vec![];
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    self.error(self.peek().clone(), "Can't have more than 255 arguments.");
+                }
+
+                arguments.push(self.expression()?);
+
+                if !self.match_tokens(vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments.")?.clone();
+        Ok(Expr::Call(Box::new(callee), paren, arguments))
+    }
+
+    fn primary(&mut self) -> ParseResult<'src, Expr<'src>> {
         if self.match_tokens(vec![TokenType::False]) {
-            return Expr::Literal(TokenLiteral::False);
+            return Ok(Expr::Literal(TokenLiteral::False, self.previous().span()));
         }
 
         if self.match_tokens(vec![TokenType::True]) {
-            return Expr::Literal(TokenLiteral::True);
+            return Ok(Expr::Literal(TokenLiteral::True, self.previous().span()));
         }
 
         if self.match_tokens(vec![TokenType::Nil]) {
-            return Expr::Literal(TokenLiteral::Nil);
+            return Ok(Expr::Literal(TokenLiteral::Nil, self.previous().span()));
         }
 
         if self.match_tokens(vec![TokenType::String, TokenType::Number]) {
-            return Expr::Literal(self.previous().clone().literal.unwrap());
+            let token = self.previous();
+            return Ok(Expr::Literal(token.literal.unwrap(), token.span()));
+        }
+
+        if self.match_tokens(vec![TokenType::Interpolation]) {
+            return self.interpolation();
+        }
+
+        if self.match_tokens(vec![TokenType::Super]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self.consume(TokenType::Identifier, "Expect superclass method name.")?.clone();
+            return Ok(Expr::Super(keyword, method, ExprId::unique()));
+        }
+
+        if self.match_tokens(vec![TokenType::This]) {
+            return Ok(Expr::This(self.previous().clone(), ExprId::unique()));
+        }
+
+        if self.match_tokens(vec![TokenType::Identifier]) {
+            return Ok(Expr::Variable(self.previous().clone(), ExprId::unique()));
         }
 
         if self.match_tokens(vec![TokenType::LeftParen]) {
-            let expr = self.expression();
-            self.consume(TokenType::RightParen, "Expect ')' after expression");
-            return  Expr::Grouping(Box::new(expr));
+            let left = self.previous().span();
+            let expr = self.expression()?;
+            let right = self.consume(TokenType::RightParen, "Expect ')' after expression.")?.span();
+            return Ok(Expr::Grouping(Box::new(expr), left.to(right)));
         }
 
-        panic!("Expect primary token")
+        Err(self.error(self.peek().clone(), "Expect expression."))
     }
 
-    fn consume(&mut self, token_type: TokenType, message: &str) {
+    /// Parses the rest of an interpolated string, whose first segment has
+    /// just been consumed.
+    fn interpolation(&mut self) -> ParseResult<'src, Expr<'src>> {
+        let start = self.previous().span();
+        let mut parts = vec![];
+
+        loop {
+            self.push_segment(&mut parts);
+            parts.push(self.expression()?);
 
+            if !self.match_tokens(vec![TokenType::Interpolation]) {
+                break;
+            }
+        }
+
+        self.consume(TokenType::InterpolationEnd, "Expect '}' after interpolated expression.")?;
+        self.push_segment(&mut parts);
+        Ok(Expr::Interpolation(parts, start.to(self.previous().span())))
+    }
+
+    /// Adds the text of the string segment just consumed, unless it is empty.
+    fn push_segment(&self, parts: &mut Vec<Expr<'src>>) {
+        let token = self.previous();
+        match token.literal {
+            Some(TokenLiteral::String(text)) if text.as_str().is_empty() => {}
+            literal => parts.push(Expr::Literal(literal.unwrap(), token.span())),
+        }
+    }
+
+    fn consume(&mut self, token_type: TokenType, message: &str) -> ParseResult<'src, &Token<'src>> {
+        if self.check(&token_type) {
+            return Ok(self.advance());
+        }
+
+        Err(self.error(self.peek().clone(), message))
+    }
+
+    fn error(&mut self, token: Token<'src>, message: &str) -> ParseError<'src> {
+        let error = ParseError::new(&token, message);
+        self.tokens.reporter().report(error.to_diagnostic());
+        self.had_error = true;
+        error
+    }
+
+    /// Discards tokens until the start of the next statement, so that one
+    /// syntax error does not cascade into a pile of bogus follow-up errors.
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().token_type == TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => {}
+            }
+
+            self.advance();
+        }
     }
 
     fn match_tokens(&mut self, token_types: Vec<TokenType>) -> bool {
@@ -112,23 +528,20 @@ impl Parse {
         false
     }
 
-    fn previous(&mut self) -> &Token {
-        &self.tokens[self.current - 1]
+    fn previous(&self) -> &Token<'src> {
+        self.tokens.previous()
     }
 
-    fn advance(&mut self) -> &Token {
-        if !self.is_at_end() {
-            self.current += 1;
-        }
-        self.previous()
+    fn advance(&mut self) -> &Token<'src> {
+        self.tokens.advance()
     }
 
     fn check(&self, token_type: &TokenType) -> bool {
-        !self.is_at_end() || self.peek().token_type == *token_type
+        !self.is_at_end() && self.peek().token_type == *token_type
     }
 
-    fn peek(&self) -> &Token {
-        &self.tokens[self.current]
+    fn peek(&self) -> &Token<'src> {
+        self.tokens.peek()
     }
 
     fn is_at_end(&self) -> bool {
@@ -136,3 +549,227 @@ impl Parse {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Diagnostic;
+    use crate::expressions::ast_printer::AstPrinter;
+    use crate::reporter::Collector;
+
+    use super::*;
+
+    fn parse(source: &str) -> Result<Vec<Stmt<'_>>, Vec<Diagnostic>> {
+        let mut collector = Collector::new();
+        let statements = Parse::new(Scanner::new(source, &mut collector)).parse();
+        statements.ok_or_else(|| collector.take())
+    }
+
+    fn parse_expr(source: &str) -> Expr<'static> {
+        // Leaked so the tree can borrow from it after this returns.
+        match parse(format!("{source};").leak()).unwrap().remove(0) {
+            Stmt::Expression(expr) => expr,
+            stmt => panic!("Expected expression statement, got {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_precedence() {
+        let expr = parse_expr("-1 + 2 * (3 - 4) == 5");
+
+        assert_eq!(AstPrinter.print(&expr), "(== (+ (- 1) (* 2 (group (- 3 4)))) 5)");
+    }
+
+    #[test]
+    fn test_assignment_is_right_associative() {
+        let expr = parse_expr("a = b = 1");
+
+        assert_eq!(AstPrinter.print(&expr), "(= a (= b 1))");
+    }
+
+    #[test]
+    fn test_statements() {
+        let statements = parse("var a = 1; { var b; print a; }").unwrap();
+
+        assert_eq!(statements.len(), 2);
+        assert!(matches!(&statements[0], Stmt::Var(name, Some(_)) if name.lexeme == "a"));
+        assert!(matches!(&statements[1], Stmt::Block(body) if body.len() == 2));
+    }
+
+    #[test]
+    fn test_logical_precedence() {
+        let expr = parse_expr("a or b and c == d");
+
+        assert_eq!(AstPrinter.print(&expr), "(or a (and b (== c d)))");
+    }
+
+    #[test]
+    fn test_dangling_else_binds_to_nearest_if() {
+        let statements = parse("if (a) if (b) print 1; else print 2;").unwrap();
+
+        match &statements[0] {
+            Stmt::If(_, inner, None) => assert!(matches!(inner.as_ref(), Stmt::If(_, _, Some(_)))),
+            stmt => panic!("Expected outer if without else, got {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_for_desugars_to_while() {
+        let statements = parse("for (var i = 0; i < 3; i = i + 1) print i;").unwrap();
+
+        match &statements[0] {
+            Stmt::Block(body) => {
+                assert!(matches!(&body[0], Stmt::Var(name, Some(_)) if name.lexeme == "i"));
+                match &body[1] {
+                    Stmt::While(_, loop_body) => assert!(matches!(loop_body.as_ref(), Stmt::Block(inner) if inner.len() == 2)),
+                    stmt => panic!("Expected while, got {:?}", stmt),
+                }
+            }
+            stmt => panic!("Expected block, got {:?}", stmt),
+        }
+
+        let statements = parse("for (;;) print 1;").unwrap();
+        assert!(matches!(&statements[0], Stmt::While(Expr::Literal(TokenLiteral::True, _), _)));
+    }
+
+    #[test]
+    fn test_expression_spans() {
+        let source = "print (1 +\n  foo.bar(2));";
+        let statements = parse(source).unwrap();
+        let Stmt::Print(expr) = &statements[0] else { panic!("Expected print statement") };
+
+        let span = expr.span();
+        assert_eq!(&source[span.start..span.end], "(1 +\n  foo.bar(2))");
+        assert_eq!((span.start_line, span.start_column, span.end_line, span.end_column), (1, 7, 2, 14));
+
+        let Expr::Grouping(inner, _) = expr else { panic!("Expected grouping") };
+        let Expr::Binary(_, _, rhs) = inner.as_ref() else { panic!("Expected binary") };
+        assert_eq!(&source[rhs.span().start..rhs.span().end], "foo.bar(2)");
+    }
+
+    #[test]
+    fn test_call() {
+        let expr = parse_expr("f(1, g(2))(3)");
+
+        assert_eq!(AstPrinter.print(&expr), "(call (call f 1 (call g 2)) 3)");
+    }
+
+    #[test]
+    fn test_function_declaration() {
+        let statements = parse("fun add(a, b) { return a + b; }").unwrap();
+
+        match &statements[0] {
+            Stmt::Function(function) => {
+                assert_eq!(function.name.lexeme, "add");
+                assert_eq!(function.params.len(), 2);
+                assert!(matches!(&function.body[0], Stmt::Return(_, Some(_))));
+            }
+            stmt => panic!("Expected function, got {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_too_many_arguments() {
+        let arguments = vec!["1"; 256].join(", ");
+        let errors = parse(&format!("f({arguments});")).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Can't have more than 255 arguments.");
+
+        let params = (0..256).map(|i| format!("p{i}")).collect::<Vec<_>>().join(", ");
+        let errors = parse(&format!("fun f({params}) {{}}")).unwrap_err();
+        assert_eq!(errors[0].message, "Can't have more than 255 parameters.");
+    }
+
+    #[test]
+    fn test_property_access() {
+        assert_eq!(AstPrinter.print(&parse_expr("a.b.c(1).d")), "(. (call (. (. a b) c) 1) d)");
+        assert_eq!(AstPrinter.print(&parse_expr("this.x = y")), "(= (. this x) y)");
+    }
+
+    #[test]
+    fn test_class_declaration() {
+        let statements = parse("class Foo { init(a) { this.a = a; } get() { return this.a; } }").unwrap();
+
+        match &statements[0] {
+            Stmt::Class(name, None, methods) => {
+                assert_eq!(name.lexeme, "Foo");
+                assert_eq!(methods.iter().map(|m| m.name.lexeme).collect::<Vec<_>>(), vec!["init", "get"]);
+            }
+            stmt => panic!("Expected class, got {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_subclass_declaration() {
+        let statements = parse("class A {} class B < A { f() { return super.f(); } }").unwrap();
+
+        assert!(matches!(&statements[1], Stmt::Class(_, Some(Expr::Variable(superclass, _)), _) if superclass.lexeme == "A"));
+    }
+
+    #[test]
+    fn test_super_requires_method() {
+        let errors = parse("class A {} class B < A { f() { super; } }").unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect '.' after 'super'.");
+    }
+
+    #[test]
+    fn test_repl_trailing_expression() {
+        let mut collector = Collector::new();
+        let statements = Parse::repl(Scanner::new("1 + 2", &mut collector)).parse().unwrap();
+
+        assert!(matches!(&statements[0], Stmt::Print(_)));
+        assert!(parse("1 + 2").is_err());
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let expr = parse_expr(r#""a ${b + 1} c ${"${d}"}""#);
+
+        assert_eq!(AstPrinter.print(&expr), r#"(interpolate "a " (+ b 1) " c " (interpolate d))"#);
+        assert_eq!(expr.span().len(), 24);
+    }
+
+    #[test]
+    fn test_unclosed_interpolation() {
+        let errors = parse(r#"print "a ${b c}";"#).unwrap_err();
+        assert_eq!(errors[0].message, "Expect '}' after interpolated expression.");
+
+        let errors = parse(r#"print "a ${}";"#).unwrap_err();
+        assert_eq!(errors[0].message, "Expect expression.");
+    }
+
+    #[test]
+    fn test_missing_expression() {
+        let errors = parse("1 +").unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Expect expression.");
+        assert_eq!(errors[0].primary.message, "at end of input");
+    }
+
+    #[test]
+    fn test_unclosed_group() {
+        let errors = parse("(1 + 2;").unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect ')' after expression.");
+    }
+
+    #[test]
+    fn test_invalid_assignment_target() {
+        let errors = parse("1 + a = 3;").unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Invalid assignment target.");
+        assert_eq!(errors[0].primary.span.start_column, 7);
+    }
+
+    #[test]
+    fn test_reports_every_error() {
+        let errors = parse("(1 +;\nvar = 2;\nprint 3;").unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].primary.span.start_line, 1);
+        assert_eq!(errors[1].message, "Expect variable name.");
+        assert_eq!(errors[1].primary.span.start_line, 2);
+    }
+}
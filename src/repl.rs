@@ -0,0 +1,196 @@
+use std::env;
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use lox_rs::reporter::Collector;
+use lox_rs::scanner::Scanner;
+use lox_rs::token::TokenType;
+
+use crate::{print_ast, print_tokens, run_bytecode, Engine, Options, EX_IOERR};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+// Kept in the home directory, so history is shared by every session.
+const HISTORY_FILE: &str = ".lox_history";
+
+const HELP: &str = "\
+Commands:
+    :tokens <code>    Print the tokens of <code>
+    :ast <code>       Print the syntax tree of <code>
+    :env              List the global variables and their values
+    :load <file>      Run a script in this session
+    :reset            Forget everything defined so far
+    :quit             Leave the prompt";
+
+/// An interactive session. Every input runs against the same engine, so
+/// declarations carry over from one input to the next.
+struct Repl<'o> {
+    options: &'o Options,
+    engine: Engine<'static>,
+}
+
+/// Runs the interactive prompt until the user quits or input ends.
+pub fn run_prompt(options: &Options) -> u8 {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(error) => {
+            eprintln!("{error}");
+            return EX_IOERR;
+        }
+    };
+
+    let history = history_path();
+    if let Some(path) = &history {
+        // There is no history file until the first session ends.
+        let _ = editor.load_history(path);
+    }
+
+    let mut repl = Repl { options, engine: Engine::new(options) };
+    let code = repl.run(&mut editor);
+
+    if let Some(path) = &history {
+        if let Err(error) = editor.save_history(path) {
+            eprintln!("Could not save history to '{}': {error}", path.display());
+        }
+    }
+    code
+}
+
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+impl Repl<'_> {
+    fn run(&mut self, editor: &mut DefaultEditor) -> u8 {
+        // Lines read so far of an input that isn't complete yet.
+        let mut input = String::new();
+
+        loop {
+            let prompt = if input.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
+            let line = match editor.readline(prompt) {
+                Ok(line) => line,
+                // Ctrl-C abandons the current input, as in a shell.
+                Err(ReadlineError::Interrupted) => {
+                    input.clear();
+                    continue;
+                }
+                Err(ReadlineError::Eof) => return 0,
+                Err(error) => {
+                    eprintln!("{error}");
+                    return EX_IOERR;
+                }
+            };
+
+            if input.is_empty() {
+                if let Some(command) = line.trim().strip_prefix(':') {
+                    let _ = editor.add_history_entry(line.trim());
+                    match self.command(command) {
+                        ControlFlow::Continue(()) => continue,
+                        ControlFlow::Break(()) => return 0,
+                    }
+                }
+            }
+
+            input.push_str(&line);
+            input.push('\n');
+            if is_incomplete(&input) {
+                continue;
+            }
+
+            if !input.trim().is_empty() {
+                let _ = editor.add_history_entry(input.trim_end());
+                self.run_source(std::mem::take(&mut input), true);
+            }
+            input.clear();
+        }
+    }
+
+    fn run_source(&mut self, source: String, repl: bool) {
+        // Errors have already been reported, and the session carries on.
+        let _ = match &mut self.engine {
+            // Functions declared in this input borrow their names and bodies
+            // from it and can be called from later inputs, so it has to live
+            // as long as the session does.
+            Engine::Tree(_) => self.engine.run(Box::leak(source.into_boxed_str()), repl),
+            Engine::Vm(vm) => run_bytecode(vm, &source, repl),
+        };
+    }
+
+    fn command(&mut self, command: &str) -> ControlFlow<()> {
+        let (name, argument) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+        let argument = argument.trim();
+
+        // Failures have already been reported, so results are ignored.
+        match name {
+            "tokens" => {
+                let _ = print_tokens(argument);
+            }
+            "ast" => {
+                let _ = print_ast(argument, true);
+            }
+            "env" => {
+                for (name, value) in self.engine.globals() {
+                    println!("{name} = {value}");
+                }
+            }
+            "load" if argument.is_empty() => eprintln!("Usage: :load <file>"),
+            "load" => match fs::read_to_string(argument) {
+                Ok(source) => self.run_source(source, false),
+                Err(error) => eprintln!("Could not read '{argument}': {error}"),
+            },
+            "reset" => self.engine = Engine::new(self.options),
+            "quit" => return ControlFlow::Break(()),
+            "help" => println!("{HELP}"),
+            _ => eprintln!("Unknown command ':{name}'.\n{HELP}"),
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Whether `source` stops inside a bracket, brace, string or comment, so the
+/// prompt should read another line before running it.
+fn is_incomplete(source: &str) -> bool {
+    // Errors are reported when the finished input runs, not here.
+    let mut collector = Collector::new();
+    let mut scanner = Scanner::new(source, &mut collector);
+
+    let mut depth = 0;
+    for token in scanner.by_ref() {
+        match token.token_type {
+            TokenType::LeftParen | TokenType::LeftBrace => depth += 1,
+            // Only the first segment of a string opens an interpolation; the
+            // ones after it, like `} and ${`, close one and open the next.
+            TokenType::Interpolation if token.lexeme.starts_with('"') => depth += 1,
+            TokenType::RightParen | TokenType::RightBrace | TokenType::InterpolationEnd => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0 || scanner.is_unterminated()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(is_incomplete("fun f() {\n"));
+        assert!(is_incomplete("print (1 +\n"));
+        assert!(is_incomplete("print \"a\n"));
+        assert!(is_incomplete("print \"${ {\n"));
+        assert!(is_incomplete("/* note\n"));
+
+        assert!(!is_incomplete("fun f() {}\n"));
+        assert!(!is_incomplete("print \"${a}\";\n"));
+        assert!(!is_incomplete("print \"${1} and ${2}\";\n"));
+        assert!(!is_incomplete("print \"${\"${a} and ${b}\"} and ${c}\";\n"));
+        assert!(is_incomplete("print \"${\"${a} and ${b\n"));
+        assert!(!is_incomplete("1 + }\n"));
+        assert!(!is_incomplete("var a = 1\n"));
+    }
+}
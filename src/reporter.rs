@@ -0,0 +1,92 @@
+use std::io::{self, IsTerminal};
+use std::mem;
+
+use crate::diagnostic::{Diagnostic, Severity};
+
+/// Receives the diagnostics produced while scanning and parsing, so each
+/// front-end pass can report problems without knowing where they end up.
+pub trait Reporter {
+    fn report(&mut self, diagnostic: Diagnostic);
+
+    fn has_errors(&self) -> bool;
+}
+
+/// Keeps every diagnostic it is given, for embedders and tests that want to
+/// inspect them rather than print them.
+#[derive(Debug, Default)]
+pub struct Collector {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Collector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn take(&mut self) -> Vec<Diagnostic> {
+        mem::take(&mut self.diagnostics)
+    }
+}
+
+impl Reporter for Collector {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+/// Prints each diagnostic to stderr as soon as it is reported, with the source
+/// it points at, colored when stderr is a terminal.
+pub struct StderrReporter<'src> {
+    source: &'src str,
+    color: bool,
+    has_errors: bool,
+}
+
+impl<'src> StderrReporter<'src> {
+    pub fn new(source: &'src str) -> Self {
+        StderrReporter {
+            source,
+            color: io::stderr().is_terminal(),
+            has_errors: false,
+        }
+    }
+}
+
+impl Reporter for StderrReporter<'_> {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        eprint!("{}", diagnostic.render(self.source, self.color));
+        self.has_errors |= diagnostic.severity == Severity::Error;
+    }
+
+    fn has_errors(&self) -> bool {
+        self.has_errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::span::Span;
+
+    use super::*;
+
+    #[test]
+    fn test_collector() {
+        let mut collector = Collector::new();
+        assert!(!collector.has_errors());
+
+        collector.report(Diagnostic::error("Oops.", Span::default()));
+        assert!(collector.has_errors());
+        assert_eq!(collector.diagnostics()[0].message, "Oops.");
+
+        assert_eq!(collector.take().len(), 1);
+        assert!(!collector.has_errors());
+    }
+}
@@ -0,0 +1,354 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::ParseError;
+use crate::expressions;
+use crate::expressions::expr::{Expr, ExprId};
+use crate::interpreter::Interpreter;
+use crate::reporter::Reporter;
+use crate::statements;
+use crate::statements::stmt::{Function, Stmt};
+use crate::symbol::Symbol;
+use crate::token::Token;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/// A static pass run between parsing and execution. It binds every local
+/// variable reference to the scope it was declared in, and reports the
+/// errors that only need the shape of the program to detect.
+pub struct Resolver<'a, 'src> {
+    interpreter: &'a Interpreter<'src>,
+    // Each scope maps a name to whether its initializer has finished.
+    scopes: RefCell<Vec<HashMap<Symbol, bool>>>,
+    current_function: Cell<FunctionType>,
+    current_class: Cell<ClassType>,
+    reporter: RefCell<&'a mut dyn Reporter>,
+}
+
+impl<'a, 'src> Resolver<'a, 'src> {
+    pub fn new(interpreter: &'a Interpreter<'src>, reporter: &'a mut dyn Reporter) -> Self {
+        Resolver {
+            interpreter,
+            scopes: RefCell::new(vec![]),
+            current_function: Cell::new(FunctionType::None),
+            current_class: Cell::new(ClassType::None),
+            reporter: RefCell::new(reporter),
+        }
+    }
+
+    pub fn resolve(&self, statements: &[Stmt<'src>]) {
+        self.resolve_statements(statements);
+    }
+
+    fn resolve_statements(&self, statements: &[Stmt<'src>]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn resolve_expr(&self, expr: &Expr<'src>) {
+        expr.accept(self);
+    }
+
+    fn resolve_function(&self, function: &Function<'src>, function_type: FunctionType) {
+        let enclosing = self.current_function.replace(function_type);
+
+        self.begin_scope();
+        for param in function.params.iter() {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_statements(&function.body);
+        self.end_scope();
+
+        self.current_function.set(enclosing);
+    }
+
+    fn resolve_class(&self, name: &Token<'src>, superclass: Option<&Expr<'src>>, methods: &[Rc<Function<'src>>]) {
+        let enclosing = self.current_class.replace(ClassType::Class);
+
+        self.declare(name);
+        self.define(name);
+
+        if let Some(superclass) = superclass {
+            if let Expr::Variable(superclass_name, _) = superclass {
+                if superclass_name.symbol() == name.symbol() {
+                    self.error(superclass_name, "A class can't inherit from itself.");
+                }
+            }
+
+            self.current_class.set(ClassType::Subclass);
+            self.resolve_expr(superclass);
+
+            self.begin_scope();
+            self.put(Symbol::SUPER);
+        }
+
+        self.begin_scope();
+        self.put(Symbol::THIS);
+
+        for method in methods {
+            let function_type = if method.name.symbol() == Symbol::INIT {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, function_type);
+        }
+
+        self.end_scope();
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class.set(enclosing);
+    }
+
+    fn resolve_local(&self, id: ExprId, name: &Token<'src>) {
+        let scopes = self.scopes.borrow();
+        if let Some(depth) = scopes.iter().rev().position(|scope| scope.contains_key(&name.symbol())) {
+            self.interpreter.resolve(id, depth);
+        }
+        // Not found in any scope: assume it is a global.
+    }
+
+    fn begin_scope(&self) {
+        self.scopes.borrow_mut().push(HashMap::new());
+    }
+
+    fn end_scope(&self) {
+        self.scopes.borrow_mut().pop();
+    }
+
+    fn declare(&self, name: &Token<'src>) {
+        let mut scopes = self.scopes.borrow_mut();
+        let Some(scope) = scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&name.symbol()) {
+            drop(scopes);
+            self.error(name, "Already a variable with this name in this scope.");
+            return;
+        }
+
+        scope.insert(name.symbol(), false);
+    }
+
+    fn define(&self, name: &Token<'src>) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(name.symbol(), true);
+        }
+    }
+
+    /// Defines a synthetic variable such as `this` in the innermost scope.
+    fn put(&self, name: Symbol) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(name, true);
+        }
+    }
+
+    fn error(&self, token: &Token<'src>, message: &str) {
+        self.reporter.borrow_mut().report(ParseError::new(token, message).to_diagnostic());
+    }
+}
+
+impl<'src> expressions::Visitor<'src, ()> for Resolver<'_, 'src> {
+    fn visit_expr(&self, expr: &Expr<'src>) {
+        match expr {
+            Expr::Assign(name, value, id) => {
+                self.resolve_expr(value);
+                self.resolve_local(*id, name);
+            }
+            Expr::Binary(lhs, _, rhs) | Expr::Logical(lhs, _, rhs) => {
+                self.resolve_expr(lhs);
+                self.resolve_expr(rhs);
+            }
+            Expr::Call(callee, _, arguments) => {
+                self.resolve_expr(callee);
+                for argument in arguments {
+                    self.resolve_expr(argument);
+                }
+            }
+            Expr::Get(object, _) => self.resolve_expr(object),
+            Expr::Grouping(expr, _) => self.resolve_expr(expr),
+            Expr::Interpolation(parts, _) => {
+                for part in parts {
+                    self.resolve_expr(part);
+                }
+            }
+            Expr::Literal(..) => {}
+            Expr::Set(object, _, value) => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            Expr::Super(keyword, _, id) => {
+                match self.current_class.get() {
+                    ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
+                    ClassType::Class => self.error(keyword, "Can't use 'super' in a class with no superclass."),
+                    ClassType::Subclass => {}
+                }
+                self.resolve_local(*id, keyword);
+            }
+            Expr::This(keyword, id) => {
+                if self.current_class.get() == ClassType::None {
+                    self.error(keyword, "Can't use 'this' outside of a class.");
+                    return;
+                }
+                self.resolve_local(*id, keyword);
+            }
+            Expr::Unary(_, rhs) => self.resolve_expr(rhs),
+            Expr::Variable(name, id) => {
+                let declared_but_undefined = self.scopes.borrow().last()
+                    .is_some_and(|scope| scope.get(&name.symbol()) == Some(&false));
+                if declared_but_undefined {
+                    self.error(name, "Can't read local variable in its own initializer.");
+                }
+                self.resolve_local(*id, name);
+            }
+        }
+    }
+}
+
+impl<'src> statements::Visitor<'src, ()> for Resolver<'_, 'src> {
+    fn visit_stmt(&self, stmt: &Stmt<'src>) {
+        match stmt {
+            Stmt::Block(statements) => {
+                self.begin_scope();
+                self.resolve_statements(statements);
+                self.end_scope();
+            }
+            Stmt::Class(name, superclass, methods) => self.resolve_class(name, superclass.as_ref(), methods),
+            Stmt::Expression(expr) | Stmt::Print(expr) => self.resolve_expr(expr),
+            Stmt::Function(function) => {
+                // Defined before the body is resolved, so the function can call itself.
+                self.declare(&function.name);
+                self.define(&function.name);
+                self.resolve_function(function, FunctionType::Function);
+            }
+            Stmt::If(condition, then_branch, else_branch) => {
+                self.resolve_expr(condition);
+                then_branch.accept(self);
+                if let Some(else_branch) = else_branch {
+                    else_branch.accept(self);
+                }
+            }
+            Stmt::Return(keyword, value) => {
+                if self.current_function.get() == FunctionType::None {
+                    self.error(keyword, "Can't return from top-level code.");
+                }
+
+                if let Some(value) = value {
+                    if self.current_function.get() == FunctionType::Initializer {
+                        self.error(keyword, "Can't return a value from an initializer.");
+                    }
+                    self.resolve_expr(value);
+                }
+            }
+            Stmt::Var(name, initializer) => {
+                self.declare(name);
+                if let Some(initializer) = initializer {
+                    self.resolve_expr(initializer);
+                }
+                self.define(name);
+            }
+            Stmt::While(condition, body) => {
+                self.resolve_expr(condition);
+                body.accept(self);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Diagnostic;
+    use crate::parser::Parse;
+    use crate::reporter::Collector;
+    use crate::scanner::Scanner;
+
+    use super::*;
+
+    fn resolve(source: &str) -> Result<(), Vec<Diagnostic>> {
+        let mut collector = Collector::new();
+        let statements = Parse::new(Scanner::new(source, &mut collector)).parse().unwrap();
+        Resolver::new(&Interpreter::new(), &mut collector).resolve(&statements);
+        if collector.has_errors() {
+            Err(collector.take())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn resolve_error(source: &str) -> String {
+        let errors = resolve(source).unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        errors[0].message.clone()
+    }
+
+    #[test]
+    fn test_valid_program() {
+        assert!(resolve("var a = 1; { var b = a; fun f(c) { return b + c; } } class A { init() { this.x = 1; } }").is_ok());
+    }
+
+    #[test]
+    fn test_globals_may_be_redeclared() {
+        assert!(resolve("var a = 1; var a = a;").is_ok());
+    }
+
+    #[test]
+    fn test_read_local_in_own_initializer() {
+        assert_eq!(resolve_error("var a = 1; { var a = a; }"), "Can't read local variable in its own initializer.");
+    }
+
+    #[test]
+    fn test_redeclare_local() {
+        assert_eq!(resolve_error("{ var a = 1; var a = 2; }"), "Already a variable with this name in this scope.");
+        assert_eq!(resolve_error("fun f(a, a) {}"), "Already a variable with this name in this scope.");
+    }
+
+    #[test]
+    fn test_top_level_return() {
+        assert_eq!(resolve_error("return 1;"), "Can't return from top-level code.");
+    }
+
+    #[test]
+    fn test_return_value_from_initializer() {
+        assert_eq!(resolve_error("class Foo { init() { return 1; } }"), "Can't return a value from an initializer.");
+
+        assert!(resolve("class Foo { init() { return; } }").is_ok());
+        assert!(resolve("class Foo { init() { fun f() { return 1; } } }").is_ok());
+    }
+
+    #[test]
+    fn test_this_outside_class() {
+        assert_eq!(resolve_error("print this;"), "Can't use 'this' outside of a class.");
+        assert_eq!(resolve_error("fun f() { return this; }"), "Can't use 'this' outside of a class.");
+    }
+
+    #[test]
+    fn test_inherit_from_itself() {
+        assert_eq!(resolve_error("class A < A {}"), "A class can't inherit from itself.");
+    }
+
+    #[test]
+    fn test_invalid_super() {
+        assert_eq!(resolve_error("super.f();"), "Can't use 'super' outside of a class.");
+        assert_eq!(resolve_error("class A { f() { super.f(); } }"), "Can't use 'super' in a class with no superclass.");
+    }
+}
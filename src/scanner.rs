@@ -1,187 +1,501 @@
-use crate::error::error;
+use std::iter::FusedIterator;
+use std::mem;
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::diagnostic::Diagnostic;
+use crate::reporter::Reporter;
+use crate::span::Span;
+use crate::symbol::Symbol;
 use crate::token::{Token, TokenLiteral, TokenType};
 
-pub struct Scanner {
-    pub source: Vec<char>,
-    pub tokens: Vec<Token>,
+/// Splits source text into tokens. It walks the UTF-8 bytes directly, and
+/// every token's lexeme is a slice of the source rather than a copy.
+///
+/// Tokens are scanned on demand as the scanner is iterated, ending with a
+/// single `Eof` token.
+pub struct Scanner<'src, 'r> {
+    source: &'src str,
+    reporter: &'r mut dyn Reporter,
+    had_error: bool,
+    unterminated: bool,
+    // The token produced by the last `scan_token`, if it produced one rather
+    // than skipping whitespace, a comment or an error.
+    scanned: Option<Token<'src>>,
+    finished: bool,
+    // For each `${` we are inside, innermost last, how many `{` have been
+    // opened since it and not yet closed. A `}` with none open ends the
+    // interpolated expression and resumes the string.
+    interpolations: Vec<usize>,
+    // Byte offsets of the start of the current token and of the next byte.
     start: usize,
     current: usize,
-    line: usize,
+    line: u32,
+    // Column of `current`, counted in characters rather than bytes.
+    column: u32,
+    // Position of `start`, captured when a token begins.
+    start_line: u32,
+    start_column: u32,
 }
 
-fn is_alpha(c: char) -> bool {
-    c.is_ascii_alphabetic() || c == '_'
+fn is_alpha(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
 }
 
-fn is_digit(c: char) -> bool {
-    c.is_digit(10)
+fn is_digit(c: u8) -> bool {
+    c.is_ascii_digit()
 }
 
-fn is_alpha_numeric(c: char) -> bool {
+fn is_alpha_numeric(c: u8) -> bool {
     is_alpha(c) || is_digit(c)
 }
 
-impl Scanner {
-    pub fn new(source: &str) -> Self {
-        let mut scanner = Scanner {
-            source: source.chars().collect::<Vec<char>>(),
-            tokens: vec![],
+/// Whether `c` can start an identifier: `_` or anything Unicode calls
+/// XID_Start, which covers letters in every script.
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || unicode_ident::is_xid_start(c)
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
+/// Whether `byte` continues a multi-byte UTF-8 sequence rather than starting
+/// a new character.
+fn is_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+impl<'src, 'r> Scanner<'src, 'r> {
+    pub fn new(source: &'src str, reporter: &'r mut dyn Reporter) -> Self {
+        Scanner {
+            source,
+            reporter,
+            had_error: false,
+            unterminated: false,
+            scanned: None,
+            finished: false,
+            interpolations: vec![],
             start: 0,
             current: 0,
             line: 1,
-        };
-        scanner.scan_tokens();
-        scanner
+            column: 1,
+            start_line: 1,
+            start_column: 1,
+        }
     }
 
-    fn scan_tokens(&mut self) {
-        while !self.is_at_end() {
-            self.start = self.current;
-            self.scan_token();
-        }
-        self.tokens.push(Token::eof(self.line));
+    /// Scans the rest of the source, for callers that want every token at once.
+    pub fn scan_all(self) -> Vec<Token<'src>> {
+        self.collect()
+    }
+
+    /// Whether any errors have been reported so far.
+    pub fn had_error(&self) -> bool {
+        self.had_error
+    }
+
+    /// Whether the source ended inside a string or block comment, so that
+    /// more input could still complete it.
+    pub fn is_unterminated(&self) -> bool {
+        self.unterminated
+    }
+
+    pub fn reporter(&mut self) -> &mut dyn Reporter {
+        self.reporter
     }
 
     fn scan_token(&mut self) {
         let c = self.advance();
         match c {
-            '(' => self.add_token_sym(TokenType::LeftParen),
-            ')' => self.add_token_sym(TokenType::RightParen),
-            '{' => self.add_token_sym(TokenType::LeftBrace),
-            '}' => self.add_token_sym(TokenType::RightBrace),
-            ',' => self.add_token_sym(TokenType::Comma),
-            '.' => self.add_token_sym(TokenType::Dot),
-            '-' => self.add_token_sym(TokenType::Minus),
-            '+' => self.add_token_sym(TokenType::Plus),
-            ';' => self.add_token_sym(TokenType::Semicolon),
-            '*' => self.add_token_sym(TokenType::Star),
-            '!' => {
-                let token_type = self.match_token('=', TokenType::BangEqual, TokenType::Bang);
+            b'(' => self.add_token_sym(TokenType::LeftParen),
+            b')' => self.add_token_sym(TokenType::RightParen),
+            b'{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token_sym(TokenType::LeftBrace)
+            }
+            b'}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.string();
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    self.add_token_sym(TokenType::RightBrace)
+                }
+                None => self.add_token_sym(TokenType::RightBrace),
+            },
+            b',' => self.add_token_sym(TokenType::Comma),
+            b'.' => self.add_token_sym(TokenType::Dot),
+            b'-' => self.add_token_sym(TokenType::Minus),
+            b'+' => self.add_token_sym(TokenType::Plus),
+            b';' => self.add_token_sym(TokenType::Semicolon),
+            b'*' => self.add_token_sym(TokenType::Star),
+            b'!' => {
+                let token_type = self.match_token(b'=', TokenType::BangEqual, TokenType::Bang);
                 self.add_token_sym(token_type)
             }
-            '=' => {
-                let token_type = self.match_token('=', TokenType::EqualEqual, TokenType::Equal);
+            b'=' => {
+                let token_type = self.match_token(b'=', TokenType::EqualEqual, TokenType::Equal);
                 self.add_token_sym(token_type)
             }
-            '<' => {
-                let token_type = self.match_token('=', TokenType::LessEqual, TokenType::Less);
+            b'<' => {
+                let token_type = self.match_token(b'=', TokenType::LessEqual, TokenType::Less);
                 self.add_token_sym(token_type);
             }
-            '>' => {
-                let token_type = self.match_token('=', TokenType::GreaterEqual, TokenType::Greater);
+            b'>' => {
+                let token_type = self.match_token(b'=', TokenType::GreaterEqual, TokenType::Greater);
                 self.add_token_sym(token_type);
             }
-            '/' => self.slash(),
-            '"' => self.string(),
-            '\n' => self.line += 1,
-            c if c != '\n' && c.is_whitespace() => {}
+            b'/' => self.slash(),
+            b'"' => self.string(),
+            c if (c as char).is_whitespace() => {}
 
             c if is_digit(c) => self.number(),
 
             c if is_alpha(c) => self.identifier(),
-            _ => error(self.line, "Unexpected character."),
+            c if c.is_ascii() => self.unexpected_character(c as char),
+            _ => self.non_ascii(),
+        }
+    }
+
+    /// Handles a character outside ASCII, which outside of strings and
+    /// comments may only start an identifier or be whitespace.
+    fn non_ascii(&mut self) {
+        while is_continuation(self.peek()) {
+            self.advance();
         }
+
+        let c = self.lexeme().chars().next().unwrap_or_default();
+        if is_identifier_start(c) {
+            self.identifier();
+        } else if !c.is_whitespace() {
+            self.unexpected_character(c);
+        }
+    }
+
+    fn unexpected_character(&mut self, c: char) {
+        let message = format!("Unexpected character '{}' (U+{:04X}).", c.escape_debug(), c as u32);
+        self.error(Diagnostic::error(&message, self.span()));
     }
 
     fn slash(&mut self) {
         match self.peek() {
-            '/' => self.line_comment(),
-            '*' => { self.block_comment(); },
+            b'/' => self.line_comment(),
+            b'*' => { self.block_comment(); },
             _ => self.add_token_sym(TokenType::Slash),
         }
     }
 
     fn line_comment(&mut self) {
         self.advance();
-        while self.peek() != '\n' && !self.is_at_end() {
+        while self.peek() != b'\n' && !self.is_at_end() {
             self.advance();
         }
     }
 
+    /// Skips a block comment whose `/` has been consumed and whose `*` is
+    /// next. Returns whether the comment was closed.
     fn block_comment(&mut self) -> bool {
+        self.advance();
         loop {
+            if self.is_at_end() {
+                let diagnostic = Diagnostic::error("Unterminated block comment", self.start_span(2))
+                    .with_primary_label("comment starts here")
+                    .with_label(self.end_span(), "input ends here")
+                    .with_help("close the comment with '*/'");
+                self.error(diagnostic);
+                self.unterminated = true;
+                break false;
+            }
+
             let c = self.advance();
-            if c == '/' {
-                if self.peek() == '*' {
-                    if !self.block_comment() {
-                        break false;
-                    }
-                }
-            } else if c == '*' {
-                if self.peek() == '/' {
-                    self.advance();
-                    break true;
+            if c == b'/' && self.peek() == b'*' {
+                if !self.block_comment() {
+                    break false;
                 }
-            } else if c == '\n' {
-                self.line += 1;
-            } else if self.is_at_end() {
-                error(self.line, "Unterminated block comment");
-                break false;
+            } else if c == b'*' && self.peek() == b'/' {
+                self.advance();
+                break true;
             }
         }
     }
 
     fn identifier(&mut self) {
-        while is_alpha_numeric(self.peek()) {
-            self.advance();
+        loop {
+            let c = self.peek();
+            if is_alpha_numeric(c) {
+                self.advance();
+            } else if !c.is_ascii() && is_identifier_continue(self.peek_char()) {
+                self.advance();
+                while is_continuation(self.peek()) {
+                    self.advance();
+                }
+            } else {
+                break;
+            }
         }
 
-        let text = self.source[self.start..self.current].iter().collect::<String>();
-        let token_type = TokenType::keyword(&text).unwrap_or(TokenType::Identifier);
-        self.add_token_sym(token_type);
+        // Names are compared by symbol, so interning the NFC form makes a
+        // precomposed 'é' and an 'e' with a combining accent the same name.
+        // The lexeme keeps the spelling from the source.
+        let text = self.lexeme();
+        let symbol = if text.is_ascii() {
+            Symbol::intern(text)
+        } else {
+            Symbol::intern(&text.nfc().collect::<String>())
+        };
+        let token_type = TokenType::keyword(symbol).unwrap_or(TokenType::Identifier);
+        self.scanned = Some(Token::word(token_type, text, symbol, self.span()));
     }
 
     fn number(&mut self) {
-        while is_digit(self.peek()) {
-            self.advance();
+        let prefix = match (self.source.as_bytes()[self.start], self.peek()) {
+            (b'0', b'x' | b'X') => Some((16, "hexadecimal", "0xFF")),
+            (b'0', b'b' | b'B') => Some((2, "binary", "0b1010")),
+            (b'0', b'o' | b'O') => Some((8, "octal", "0o17")),
+            _ => None,
+        };
+
+        match prefix {
+            Some((radix, name, example)) => self.radix_number(radix, name, example),
+            None => self.decimal_number(),
         }
+    }
+
+    /// Scans the rest of a decimal number, whose first digit has been consumed.
+    fn decimal_number(&mut self) {
+        // The literal without separators, in the form `f64::from_str` takes.
+        let mut text = self.lexeme().to_string();
+        text.push_str(&self.digits(10));
 
-        if self.peek() == '.' && is_digit(self.peek_next()) {
+        if self.peek() == b'.' && is_digit(self.peek_next()) {
             self.advance();
+            text.push('.');
+            text.push_str(&self.digits(10));
+        } else if self.peek() == b'.' && !is_alpha(self.peek_next()) {
+            // A letter after the dot is a property access, which is at least
+            // valid syntax. Anything else is a number cut short.
+            self.advance();
+            let diagnostic = Diagnostic::error("Missing digits after decimal point", self.span())
+                .with_help(&format!("write '{text}.0', or '{text}' for a whole number"));
+            self.error(diagnostic);
         }
 
-        while is_digit(self.peek()) {
+        if matches!(self.peek(), b'e' | b'E') {
+            let start = self.end_span();
             self.advance();
+            let sign = if matches!(self.peek(), b'+' | b'-') { self.advance() as char } else { '+' };
+
+            let exponent = self.digits(10);
+            if exponent.is_empty() {
+                let diagnostic = Diagnostic::error("Missing digits in exponent", self.span_from(start))
+                    .with_help("write exponents like 6.02e23 or 1E-9");
+                self.error(diagnostic);
+            } else {
+                text.push('e');
+                text.push(sign);
+                text.push_str(&exponent);
+            }
+        }
+
+        let value = text.parse().expect("only valid digits were kept");
+        self.add_token(TokenType::Number, Some(TokenLiteral::Number(value)));
+    }
+
+    /// Scans the rest of a number in `radix`, whose `0` has been consumed and
+    /// whose prefix letter is next.
+    fn radix_number(&mut self, radix: u32, name: &str, example: &str) {
+        self.advance();
+        let digits = self.digits(radix);
+
+        if is_alpha_numeric(self.peek()) {
+            let start = self.end_span();
+            let c = self.peek() as char;
+            while is_alpha_numeric(self.peek()) {
+                self.advance();
+            }
+            let diagnostic = Diagnostic::error(&format!("Invalid digit '{c}' in {name} literal"), self.span_from(start))
+                .with_help(&format!("{name} literals look like {example}"));
+            self.error(diagnostic);
+        } else if digits.is_empty() {
+            let diagnostic = Diagnostic::error(&format!("Missing digits after '{}'", self.lexeme()), self.span())
+                .with_help(&format!("{name} literals look like {example}"));
+            self.error(diagnostic);
         }
 
-        self.add_token_number();
+        // Accumulating in an f64 loses precision past 2^53 rather than
+        // overflowing, which is what the resulting number would do anyway.
+        let value = digits.chars()
+            .filter_map(|digit| digit.to_digit(radix))
+            .fold(0.0, |value, digit| value * radix as f64 + digit as f64);
+        self.add_token(TokenType::Number, Some(TokenLiteral::Number(value)));
+    }
+
+    /// Consumes digits in `radix` and the `_` separators between them, and
+    /// returns the digits alone. Separators must each sit between two digits.
+    fn digits(&mut self, radix: u32) -> String {
+        let mut digits = String::new();
+        loop {
+            let c = self.peek();
+            if (c as char).is_digit(radix) {
+                digits.push(self.advance() as char);
+            } else if c == b'_' {
+                let start = self.end_span();
+                self.advance();
+                if self.peek() == b'_' {
+                    while self.peek() == b'_' {
+                        self.advance();
+                    }
+                    let diagnostic = Diagnostic::error("Repeated '_' in number literal", self.span_from(start))
+                        .with_help("separate digits with a single '_'");
+                    self.error(diagnostic);
+                } else if !(self.peek() as char).is_digit(radix) {
+                    let diagnostic = Diagnostic::error("Trailing '_' in number literal", self.span_from(start))
+                        .with_help("'_' can only separate digits");
+                    self.error(diagnostic);
+                }
+            } else {
+                return digits;
+            }
+        }
     }
 
+    /// Scans a string, or the next segment of an interpolated one. A segment
+    /// that stops at `${` becomes an `Interpolation` token, and the string
+    /// carries on after the matching `}` up to an `InterpolationEnd`.
     fn string(&mut self) {
-        while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+        // The decoded value, built up as escapes are found. Runs of plain
+        // text are copied from `plain` onwards in one go.
+        let mut value = String::new();
+        let mut plain = self.current;
+
+        while self.peek() != b'"' && !self.is_at_end() {
+            if self.peek() == b'$' && self.peek_next() == b'{' {
+                value.push_str(&self.source[plain..self.current]);
+                self.advance();
+                self.advance();
+                self.interpolations.push(0);
+                let literal = TokenLiteral::String(Symbol::intern(&value));
+                self.add_token(TokenType::Interpolation, Some(literal));
+                return;
+            }
+
+            if self.peek() == b'\\' {
+                value.push_str(&self.source[plain..self.current]);
+                if let Some(c) = self.escape() {
+                    value.push(c);
+                }
+                plain = self.current;
+            } else {
+                self.advance();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            error(self.line, "Unterminated string");
+            let diagnostic = Diagnostic::error("Unterminated string", self.start_span(1))
+                .with_primary_label("string starts here")
+                .with_label(self.end_span(), "input ends here")
+                .with_help("close the string with '\"'");
+            self.error(diagnostic);
+            self.unterminated = true;
             return;
         }
 
+        value.push_str(&self.source[plain..self.current]);
         self.advance();
 
-        self.add_token_string();
+        let token_type = match self.source.as_bytes()[self.start] {
+            b'}' => TokenType::InterpolationEnd,
+            _ => TokenType::String,
+        };
+        self.add_token(token_type, Some(TokenLiteral::String(Symbol::intern(&value))));
     }
 
-    fn peek(&self) -> char {
+    /// Decodes the escape sequence starting at the backslash under `current`.
+    /// Reports a bad escape and returns `None`, leaving the string to carry on
+    /// after it.
+    fn escape(&mut self) -> Option<char> {
+        let start = self.end_span();
+        self.advance();
         if self.is_at_end() {
-            '\0'
-        } else {
-            self.source[self.current]
+            return None;
         }
+
+        let c = match self.advance() {
+            b'n' => '\n',
+            b't' => '\t',
+            b'r' => '\r',
+            b'\\' => '\\',
+            b'"' => '"',
+            b'0' => '\0',
+            b'$' => '$',
+            b'u' => return self.unicode_escape(start),
+            c => {
+                // Take the whole of a multi-byte character, so the span ends
+                // on a character boundary.
+                if !c.is_ascii() {
+                    while is_continuation(self.peek()) {
+                        self.advance();
+                    }
+                }
+                let diagnostic = Diagnostic::error("Unknown escape sequence", self.span_from(start))
+                    .with_help("valid escapes are \\n, \\t, \\r, \\\\, \\\", \\0, \\$ and \\u{XXXX}");
+                self.error(diagnostic);
+                return None;
+            }
+        };
+        Some(c)
     }
 
-    fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            '\0'
-        } else {
-            self.source[self.current + 1]
+    /// Decodes the rest of a `\u{XXXX}` escape, after the `u`.
+    fn unicode_escape(&mut self, start: Span) -> Option<char> {
+        if !self.match_char(b'{') {
+            let diagnostic = Diagnostic::error("Invalid unicode escape", self.span_from(start))
+                .with_help("write unicode escapes as \\u{XXXX}, with 1 to 6 hex digits");
+            self.error(diagnostic);
+            return None;
+        }
+
+        let digits = self.current;
+        while self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let hex = &self.source[digits..self.current];
+
+        if hex.is_empty() || hex.len() > 6 || !self.match_char(b'}') {
+            let diagnostic = Diagnostic::error("Invalid unicode escape", self.span_from(start))
+                .with_help("write unicode escapes as \\u{XXXX}, with 1 to 6 hex digits");
+            self.error(diagnostic);
+            return None;
         }
+
+        let code = u32::from_str_radix(hex, 16).expect("only hex digits were consumed");
+        let c = char::from_u32(code);
+        if c.is_none() {
+            let diagnostic = Diagnostic::error("Invalid code point", self.span_from(start))
+                .with_primary_label(&format!("U+{code:04X} is not a Unicode scalar value"));
+            self.error(diagnostic);
+        }
+        c
+    }
+
+    fn peek(&self) -> u8 {
+        self.source.as_bytes().get(self.current).copied().unwrap_or(b'\0')
+    }
+
+    /// The whole character at `current`, for when `peek` finds a non-ASCII byte.
+    fn peek_char(&self) -> char {
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
-    fn match_token(&mut self, expected_char: char,
+    fn peek_next(&self) -> u8 {
+        self.source.as_bytes().get(self.current + 1).copied().unwrap_or(b'\0')
+    }
+
+    fn match_token(&mut self, expected_char: u8,
                    expected_token_type: TokenType,
                    otherwise_token_type: TokenType) -> TokenType {
         if self.match_char(expected_char) {
@@ -191,46 +505,98 @@ impl Scanner {
         }
     }
 
-    fn match_char(&mut self, expected: char) -> bool {
+    fn match_char(&mut self, expected: u8) -> bool {
         if self.is_at_end() {
             return false;
         }
 
-        if self.source[self.current] != expected {
+        if self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
+        self.advance();
         true
     }
 
-    fn advance(&mut self) -> char {
-        let pos = self.current;
+    fn advance(&mut self) -> u8 {
+        let c = self.source.as_bytes()[self.current];
         self.current += 1;
-        self.source[pos]
+        if c == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if !is_continuation(c) {
+            self.column += 1;
+        }
+        c
     }
 
-    fn add_token_sym(&mut self, token_type: TokenType) {
-        let text = self.source[self.start..self.current].iter().collect::<String>();
-        self.add_token(token_type, text, None)
+    fn error(&mut self, diagnostic: Diagnostic) {
+        self.reporter.report(diagnostic);
+        self.had_error = true;
     }
 
-    fn add_token_string(&mut self) {
-        let text = self.source[self.start..self.current].iter().collect::<String>();
-        let value = self.source[self.start + 1..self.current - 1].iter().collect::<String>();
-        self.add_token(TokenType::String, text, Some(TokenLiteral::String(value)))
+    fn mark_start(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column;
     }
 
-    fn add_token_number(&mut self) {
-        let text = self.source[self.start..self.current].iter().collect::<String>();
-        match text.parse() {
-            Ok(num) => self.add_token(TokenType::Number, text, Some(TokenLiteral::Number(num))),
-            Err(_) => error(self.line, "Failed to parse number literal"),
+    /// The first `len` bytes of the current token, which must all be ASCII on one line.
+    fn start_span(&self, len: usize) -> Span {
+        Span {
+            start: self.start,
+            end: self.start + len,
+            start_line: self.start_line,
+            start_column: self.start_column,
+            end_line: self.start_line,
+            end_column: self.start_column + len as u32,
         }
     }
 
-    fn add_token(&mut self, token_type: TokenType, text: String, literal: Option<TokenLiteral>) {
-        self.tokens.push(Token::new(token_type, text, literal, self.line));
+    /// A zero-width span at `current`.
+    fn end_span(&self) -> Span {
+        Span {
+            start: self.current,
+            end: self.current,
+            start_line: self.line,
+            start_column: self.column,
+            end_line: self.line,
+            end_column: self.column,
+        }
+    }
+
+    /// The span from the start of the current token up to `current`.
+    fn span(&self) -> Span {
+        Span {
+            start: self.start,
+            end: self.current,
+            start_line: self.start_line,
+            start_column: self.start_column,
+            end_line: self.line,
+            end_column: self.column,
+        }
+    }
+
+    /// The span from the start of `from` up to `current`.
+    fn span_from(&self, from: Span) -> Span {
+        Span {
+            end: self.current,
+            end_line: self.line,
+            end_column: self.column,
+            ..from
+        }
+    }
+
+    fn lexeme(&self) -> &'src str {
+        &self.source[self.start..self.current]
+    }
+
+    fn add_token_sym(&mut self, token_type: TokenType) {
+        self.add_token(token_type, None)
+    }
+
+    fn add_token(&mut self, token_type: TokenType, literal: Option<TokenLiteral>) {
+        self.scanned = Some(Token::new(token_type, self.lexeme(), literal, self.span()));
     }
 
     fn is_at_end(&self) -> bool {
@@ -238,192 +604,539 @@ impl Scanner {
     }
 }
 
+impl<'src> Iterator for Scanner<'src, '_> {
+    type Item = Token<'src>;
+
+    fn next(&mut self) -> Option<Token<'src>> {
+        while !self.is_at_end() {
+            self.mark_start();
+            self.scan_token();
+            if let Some(token) = self.scanned.take() {
+                return Some(token);
+            }
+        }
+
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        self.mark_start();
+        Some(Token::eof(self.span()))
+    }
+}
+
+impl FusedIterator for Scanner<'_, '_> {}
+
+/// A token stream with one token of lookahead, which is all the parsers
+/// need. It reports through the scanner's reporter, so parse errors and scan
+/// errors come out in source order.
+pub struct TokenStream<'src, 'r> {
+    scanner: Scanner<'src, 'r>,
+    current: Token<'src>,
+    previous: Token<'src>,
+}
+
+impl<'src, 'r> TokenStream<'src, 'r> {
+    pub fn new(mut scanner: Scanner<'src, 'r>) -> Self {
+        let current = scanner.next().expect("a scanner yields at least Eof");
+        TokenStream {
+            scanner,
+            previous: current.clone(),
+            current,
+        }
+    }
+
+    /// The next token, without consuming it.
+    pub fn peek(&self) -> &Token<'src> {
+        &self.current
+    }
+
+    /// The token most recently consumed.
+    pub fn previous(&self) -> &Token<'src> {
+        &self.previous
+    }
+
+    /// Consumes the next token and returns it. `Eof` is never consumed, so at
+    /// the end this leaves both tokens where they are.
+    pub fn advance(&mut self) -> &Token<'src> {
+        if let Some(next) = self.scanner.next() {
+            self.previous = mem::replace(&mut self.current, next);
+        }
+        &self.previous
+    }
+
+    pub fn had_error(&self) -> bool {
+        self.scanner.had_error()
+    }
+
+    pub fn reporter(&mut self) -> &mut dyn Reporter {
+        self.scanner.reporter()
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::error::has_error;
+    use crate::reporter::Collector;
 
     use super::*;
 
+    struct Scanned<'src> {
+        tokens: Vec<Token<'src>>,
+        diagnostics: Vec<Diagnostic>,
+    }
+
+    fn scan(source: &str) -> Scanned<'_> {
+        let mut collector = Collector::new();
+        let tokens = Scanner::new(source, &mut collector).scan_all();
+        Scanned { tokens, diagnostics: collector.take() }
+    }
+
+    /// The span of a token that starts and ends on `line`.
+    fn span(start: usize, end: usize, line: u32, column: u32) -> Span {
+        Span { start, end, start_line: line, start_column: column, end_line: line, end_column: column + (end - start) as u32 }
+    }
+
     #[test]
     fn test_eof() {
-        let scanner = Scanner::new("");
+        let scanner = scan("");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
 
         assert_eq!(scanner.tokens[0],
-                   Token::new(TokenType::Eof, String::from(""), None, 1));
+                   Token::new(TokenType::Eof, "", None, span(0, 0, 1, 1)));
     }
 
     #[test]
     fn test_single_char_op() {
-        let scanner = Scanner::new("+");
+        let scanner = scan("+");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
 
         assert_eq!(scanner.tokens.len(), 2);
         assert_eq!(scanner.tokens[0],
-                   Token::new(TokenType::Plus, String::from("+"), None, 1));
+                   Token::new(TokenType::Plus, "+", None, span(0, 1, 1, 1)));
         assert_eq!(scanner.tokens[1],
-                   Token::new(TokenType::Eof, String::from(""), None, 1));
+                   Token::new(TokenType::Eof, "", None, span(1, 1, 1, 2)));
     }
 
     #[test]
     fn test_two_char_op() {
-        let scanner = Scanner::new("!=");
+        let scanner = scan("!=");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
 
         assert_eq!(scanner.tokens[0],
-                   Token::new(TokenType::BangEqual, String::from("!="), None, 1));
+                   Token::new(TokenType::BangEqual, "!=", None, span(0, 2, 1, 1)));
     }
 
     #[test]
     fn test_comment() {
-        let scanner = Scanner::new("// comment line");
+        let scanner = scan("// comment line");
 
-        dbg!(&scanner.tokens);
-
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
         assert_eq!(scanner.tokens.len(), 1);
     }
 
     #[test]
     fn test_single_line_string_literal() {
-        let scanner = Scanner::new("\"hello world\"");
+        let scanner = scan("\"hello world\"");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
         assert_eq!(scanner.tokens[0],
-                   Token::new(TokenType::String, String::from("\"hello world\""),
-                              Some(TokenLiteral::String(String::from("hello world"))), 1));
+                   Token::new(TokenType::String, "\"hello world\"",
+                              Some(TokenLiteral::String(Symbol::intern("hello world"))), span(0, 13, 1, 1)));
     }
 
     #[test]
     fn test_multi_line_string_literal() {
-        let scanner = Scanner::new("\"hello\n\nworld\"");
+        let scanner = scan("\"hello\n\nworld\"");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
         assert_eq!(scanner.tokens[0],
-                   Token::new(TokenType::String, String::from("\"hello\n\nworld\""),
-                              Some(TokenLiteral::String(String::from("hello\n\nworld"))), 3));
+                   Token::new(TokenType::String, "\"hello\n\nworld\"",
+                              Some(TokenLiteral::String(Symbol::intern("hello\n\nworld"))),
+                              Span { start: 0, end: 14, start_line: 1, start_column: 1, end_line: 3, end_column: 7 }));
     }
 
     #[test]
     fn test_uncompleted_string() {
-        let _ = Scanner::new("\"hello");
+        let scanner = scan("\"hello");
+
+        assert_eq!(scanner.diagnostics.len(), 1);
+        assert_eq!(scanner.diagnostics[0].message, "Unterminated string");
+        assert_eq!(scanner.diagnostics[0].primary.span.start_column, 1);
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let scanner = scan(r#""a\n\t\r\\\"\0\u{48}\u{1F600}é""#);
 
-        assert!(has_error());
+        assert!(scanner.diagnostics.is_empty());
+        assert_eq!(scanner.tokens[0].literal, Some(TokenLiteral::String(Symbol::intern("a\n\t\r\\\"\0H\u{1F600}é"))));
+        assert_eq!(scanner.tokens[0].lexeme, r#""a\n\t\r\\\"\0\u{48}\u{1F600}é""#);
+    }
+
+    #[test]
+    fn test_bad_escapes() {
+        let scanner = scan(r#""\q" "\u{}" "\u48" "\u{1234567}" "\u{D800}" "\u{110000}" "ok""#);
+
+        let messages: Vec<_> = scanner.diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, vec![
+            "Unknown escape sequence",
+            "Invalid unicode escape",
+            "Invalid unicode escape",
+            "Invalid unicode escape",
+            "Invalid code point",
+            "Invalid code point",
+        ]);
+        assert_eq!(scanner.diagnostics[0].primary.span.start_column, 2);
+        assert_eq!(scanner.diagnostics[0].primary.span.len(), 2);
+        assert_eq!(scanner.diagnostics[4].primary.span.len(), 8);
+        assert_eq!(scanner.tokens.len(), 8);
+        assert_eq!(scanner.tokens[6].literal, Some(TokenLiteral::String(Symbol::intern("ok"))));
+    }
+
+    #[test]
+    fn test_interpolated_string_segments() {
+        let scanner = scan(r#""a ${x} b ${ {y} } c" "\${z}""#);
+
+        assert!(scanner.diagnostics.is_empty());
+        let string = |text| Some(TokenLiteral::String(Symbol::intern(text)));
+        assert_eq!(scanner.tokens, vec![
+            Token::new(TokenType::Interpolation, "\"a ${", string("a "), span(0, 5, 1, 1)),
+            Token::new(TokenType::Identifier, "x", None, span(5, 6, 1, 6)),
+            Token::new(TokenType::Interpolation, "} b ${", string(" b "), span(6, 12, 1, 7)),
+            Token::new(TokenType::LeftBrace, "{", None, span(13, 14, 1, 14)),
+            Token::new(TokenType::Identifier, "y", None, span(14, 15, 1, 15)),
+            Token::new(TokenType::RightBrace, "}", None, span(15, 16, 1, 16)),
+            Token::new(TokenType::InterpolationEnd, "} c\"", string(" c"), span(17, 21, 1, 18)),
+            Token::new(TokenType::String, "\"\\${z}\"", string("${z}"), span(22, 29, 1, 23)),
+            Token::new(TokenType::Eof, "", None, span(29, 29, 1, 30)),
+        ]);
+    }
+
+    #[test]
+    fn test_nested_interpolation() {
+        let scanner = scan(r#""<${ "(${x})" }>""#);
+
+        assert!(scanner.diagnostics.is_empty());
+        let types: Vec<_> = scanner.tokens.iter().map(|token| token.token_type).collect();
+        assert_eq!(types, vec![
+            TokenType::Interpolation,
+            TokenType::Interpolation,
+            TokenType::Identifier,
+            TokenType::InterpolationEnd,
+            TokenType::InterpolationEnd,
+            TokenType::Eof,
+        ]);
+        assert_eq!(scanner.tokens[4].lexeme, "}>\"");
+    }
+
+    #[test]
+    fn test_unterminated_input() {
+        for (source, unterminated) in [("\"abc", true), ("/* a /* b */", true), ("/*/", true), ("{ (", false), ("\"a\" // b", false)] {
+            let mut collector = Collector::new();
+            let mut scanner = Scanner::new(source, &mut collector);
+            scanner.by_ref().for_each(drop);
+            assert_eq!(scanner.is_unterminated(), unterminated, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_errors_stay_with_their_scan() {
+        let bad = scan("var a = @;");
+        let good = scan("var a = 1;");
+
+        assert_eq!(bad.diagnostics.len(), 1);
+        assert_eq!(bad.diagnostics[0].message, "Unexpected character '@' (U+0040).");
+        assert_eq!(bad.diagnostics[0].primary.span.start_column, 9);
+        assert!(good.diagnostics.is_empty());
     }
 
     #[test]
     fn test_int_number_literal() {
-        let scanner = Scanner::new("1234");
+        let scanner = scan("1234");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
         assert_eq!(scanner.tokens[0],
-                   Token::new(TokenType::Number, String::from("1234"),
-                              Some(TokenLiteral::Number(1234.0)), 1));
+                   Token::new(TokenType::Number, "1234",
+                              Some(TokenLiteral::Number(1234.0)), span(0, 4, 1, 1)));
     }
 
     #[test]
     fn test_float_number_literal() {
-        let scanner = Scanner::new("1234.56");
+        let scanner = scan("1234.56");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
         assert_eq!(scanner.tokens[0],
-                   Token::new(TokenType::Number, String::from("1234.56"),
-                              Some(TokenLiteral::Number(1234.56)), 1));
+                   Token::new(TokenType::Number, "1234.56",
+                              Some(TokenLiteral::Number(1234.56)), span(0, 7, 1, 1)));
+    }
+
+    #[test]
+    fn test_extended_number_literals() {
+        let scanner = scan("0xFF 0Xff_ff 0b1010 0o17 1_000_000 6.02e23 1E-9 2e+3 0.5_5 007 1.foo");
+
+        assert!(scanner.diagnostics.is_empty());
+        let numbers: Vec<_> = scanner.tokens.iter()
+            .filter_map(|token| match token.literal {
+                Some(TokenLiteral::Number(n)) => Some(n),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(numbers, vec![255.0, 65535.0, 10.0, 15.0, 1_000_000.0, 6.02e23, 1e-9, 2000.0, 0.55, 7.0, 1.0]);
+        assert_eq!(scanner.tokens[1].lexeme, "0Xff_ff");
+        assert_eq!(scanner.tokens[11].token_type, TokenType::Dot);
+    }
+
+    #[test]
+    fn test_malformed_number_literals() {
+        let cases = [
+            ("0x", "Missing digits after '0x'", 1, 2),
+            ("0b102", "Invalid digit '2' in binary literal", 5, 1),
+            ("0xFG", "Invalid digit 'G' in hexadecimal literal", 4, 1),
+            ("1e", "Missing digits in exponent", 2, 1),
+            ("1e-;", "Missing digits in exponent", 2, 2),
+            ("1__0", "Repeated '_' in number literal", 2, 2),
+            ("1_", "Trailing '_' in number literal", 2, 1),
+            ("1_.5", "Trailing '_' in number literal", 2, 1),
+            ("1.", "Missing digits after decimal point", 1, 2),
+            ("1.;", "Missing digits after decimal point", 1, 2),
+        ];
+
+        for (source, message, column, len) in cases {
+            let scanner = scan(source);
+            assert_eq!(scanner.diagnostics.len(), 1, "{source}");
+            let diagnostic = &scanner.diagnostics[0];
+            assert_eq!(diagnostic.message, message, "{source}");
+            assert_eq!(diagnostic.primary.span.start_column, column, "{source}");
+            assert_eq!(diagnostic.primary.span.len(), len, "{source}");
+            assert_eq!(scanner.tokens[0].token_type, TokenType::Number, "{source}");
+        }
     }
 
     #[test]
     fn test_keyword() {
-        let scanner = Scanner::new("class");
+        let scanner = scan("class");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
         assert_eq!(scanner.tokens[0],
-                   Token::new(TokenType::Class, String::from("class"), None, 1));
+                   Token::new(TokenType::Class, "class", None, span(0, 5, 1, 1)));
     }
 
     #[test]
     fn test_identifier() {
-        let scanner = Scanner::new("classic");
+        let scanner = scan("classic");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
         assert_eq!(scanner.tokens[0],
-                   Token::new(TokenType::Identifier, String::from("classic"), None, 1));
+                   Token::new(TokenType::Identifier, "classic", None, span(0, 7, 1, 1)));
     }
 
     #[test]
     fn test_statement() {
-        let scanner = Scanner::new("var language = \"lox\";\nvar a = 10.2;\n");
+        let scanner = scan("var language = \"lox\";\nvar a = 10.2;\n");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
         assert_eq!(scanner.tokens, vec![
-            Token::new(TokenType::Var, String::from("var"), None, 1),
-            Token::new(TokenType::Identifier, String::from("language"), None, 1),
-            Token::new(TokenType::Equal, String::from("="), None, 1),
-            Token::new(TokenType::String, String::from("\"lox\""), Some(TokenLiteral::String(String::from("lox"))), 1),
-            Token::new(TokenType::Semicolon, String::from(";"), None, 1),
-
-            Token::new(TokenType::Var, String::from("var"), None, 2),
-            Token::new(TokenType::Identifier, String::from("a"), None, 2),
-            Token::new(TokenType::Equal, String::from("="), None, 2),
-            Token::new(TokenType::Number, String::from("10.2"), Some(TokenLiteral::Number(10.2)), 2),
-            Token::new(TokenType::Semicolon, String::from(";"), None, 2),
-
-            Token::new(TokenType::Eof, String::from(""), None, 3),
+            Token::new(TokenType::Var, "var", None, span(0, 3, 1, 1)),
+            Token::new(TokenType::Identifier, "language", None, span(4, 12, 1, 5)),
+            Token::new(TokenType::Equal, "=", None, span(13, 14, 1, 14)),
+            Token::new(TokenType::String, "\"lox\"", Some(TokenLiteral::String(Symbol::intern("lox"))), span(15, 20, 1, 16)),
+            Token::new(TokenType::Semicolon, ";", None, span(20, 21, 1, 21)),
+
+            Token::new(TokenType::Var, "var", None, span(22, 25, 2, 1)),
+            Token::new(TokenType::Identifier, "a", None, span(26, 27, 2, 5)),
+            Token::new(TokenType::Equal, "=", None, span(28, 29, 2, 7)),
+            Token::new(TokenType::Number, "10.2", Some(TokenLiteral::Number(10.2)), span(30, 34, 2, 9)),
+            Token::new(TokenType::Semicolon, ";", None, span(34, 35, 2, 13)),
+
+            Token::new(TokenType::Eof, "", None, span(36, 36, 3, 1)),
         ]);
     }
 
     #[test]
     fn test_block_comment() {
-        let scanner = Scanner::new("/* comment */");
+        let scanner = scan("/* comment */");
 
-        assert!(!has_error());
-        assert_eq!(scanner.tokens, vec![Token::new(TokenType::Eof, String::from(""), None, 1),]);
+        assert!(scanner.diagnostics.is_empty());
+        assert_eq!(scanner.tokens, vec![Token::new(TokenType::Eof, "", None, span(13, 13, 1, 14)),]);
     }
 
     #[test]
     fn test_block_comment_in_code() {
-        let scanner = Scanner::new("var x = \"lox\";\n/* comment */var a = 10.2;\n");
+        let scanner = scan("var x = \"lox\";\n/* comment */var a = 10.2;\n");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
         assert_eq!(scanner.tokens, vec![
-            Token::new(TokenType::Var, String::from("var"), None, 1),
-            Token::new(TokenType::Identifier, String::from("x"), None, 1),
-            Token::new(TokenType::Equal, String::from("="), None, 1),
-            Token::new(TokenType::String, String::from("\"lox\""), Some(TokenLiteral::String(String::from("lox"))), 1),
-            Token::new(TokenType::Semicolon, String::from(";"), None, 1),
-
-            Token::new(TokenType::Var, String::from("var"), None, 2),
-            Token::new(TokenType::Identifier, String::from("a"), None, 2),
-            Token::new(TokenType::Equal, String::from("="), None, 2),
-            Token::new(TokenType::Number, String::from("10.2"), Some(TokenLiteral::Number(10.2)), 2),
-            Token::new(TokenType::Semicolon, String::from(";"), None, 2),
-
-            Token::new(TokenType::Eof, String::from(""), None, 3),
+            Token::new(TokenType::Var, "var", None, span(0, 3, 1, 1)),
+            Token::new(TokenType::Identifier, "x", None, span(4, 5, 1, 5)),
+            Token::new(TokenType::Equal, "=", None, span(6, 7, 1, 7)),
+            Token::new(TokenType::String, "\"lox\"", Some(TokenLiteral::String(Symbol::intern("lox"))), span(8, 13, 1, 9)),
+            Token::new(TokenType::Semicolon, ";", None, span(13, 14, 1, 14)),
+
+            Token::new(TokenType::Var, "var", None, span(28, 31, 2, 14)),
+            Token::new(TokenType::Identifier, "a", None, span(32, 33, 2, 18)),
+            Token::new(TokenType::Equal, "=", None, span(34, 35, 2, 20)),
+            Token::new(TokenType::Number, "10.2", Some(TokenLiteral::Number(10.2)), span(36, 40, 2, 22)),
+            Token::new(TokenType::Semicolon, ";", None, span(40, 41, 2, 26)),
+
+            Token::new(TokenType::Eof, "", None, span(42, 42, 3, 1)),
         ]);
     }
 
     #[test]
     fn test_nested_block_comment() {
-        let scanner = Scanner::new("var a = \"a\";\n/* com /* ok */ ment */var a = 10.2;\n");
+        let scanner = scan("var a = \"a\";\n/* com /* ok */ ment */var a = 10.2;\n");
 
-        assert!(!has_error());
+        assert!(scanner.diagnostics.is_empty());
         assert_eq!(scanner.tokens, vec![
-            Token::new(TokenType::Var, String::from("var"), None, 1),
-            Token::new(TokenType::Identifier, String::from("a"), None, 1),
-            Token::new(TokenType::Equal, String::from("="), None, 1),
-            Token::new(TokenType::String, String::from("\"a\""), Some(TokenLiteral::String(String::from("a"))), 1),
-            Token::new(TokenType::Semicolon, String::from(";"), None, 1),
-
-            Token::new(TokenType::Var, String::from("var"), None, 2),
-            Token::new(TokenType::Identifier, String::from("a"), None, 2),
-            Token::new(TokenType::Equal, String::from("="), None, 2),
-            Token::new(TokenType::Number, String::from("10.2"), Some(TokenLiteral::Number(10.2)), 2),
-            Token::new(TokenType::Semicolon, String::from(";"), None, 2),
-
-            Token::new(TokenType::Eof, String::from(""), None, 3),
+            Token::new(TokenType::Var, "var", None, span(0, 3, 1, 1)),
+            Token::new(TokenType::Identifier, "a", None, span(4, 5, 1, 5)),
+            Token::new(TokenType::Equal, "=", None, span(6, 7, 1, 7)),
+            Token::new(TokenType::String, "\"a\"", Some(TokenLiteral::String(Symbol::intern("a"))), span(8, 11, 1, 9)),
+            Token::new(TokenType::Semicolon, ";", None, span(11, 12, 1, 12)),
+
+            Token::new(TokenType::Var, "var", None, span(36, 39, 2, 24)),
+            Token::new(TokenType::Identifier, "a", None, span(40, 41, 2, 28)),
+            Token::new(TokenType::Equal, "=", None, span(42, 43, 2, 30)),
+            Token::new(TokenType::Number, "10.2", Some(TokenLiteral::Number(10.2)), span(44, 48, 2, 32)),
+            Token::new(TokenType::Semicolon, ";", None, span(48, 49, 2, 36)),
+
+            Token::new(TokenType::Eof, "", None, span(50, 50, 3, 1)),
         ]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_token_span() {
+        let scanner = scan("var ab\n  = 10;");
+
+        assert_eq!(scanner.tokens[1].span(), Span {
+            start: 4,
+            end: 6,
+            start_line: 1,
+            start_column: 5,
+            end_line: 1,
+            end_column: 7,
+        });
+        assert_eq!(scanner.tokens[2].line(), 2);
+        assert_eq!(scanner.tokens[2].column(), 3);
+        assert_eq!(scanner.tokens[3].span().start, 11);
+        assert_eq!(scanner.tokens[5].span(), Span {
+            start: 14,
+            end: 14,
+            start_line: 2,
+            start_column: 8,
+            end_line: 2,
+            end_column: 8,
+        });
+    }
+
+    #[test]
+    fn test_multi_line_string_span() {
+        let scanner = scan("x = \"a\nbc\"");
+        let span = scanner.tokens[2].span();
+
+        assert_eq!((span.start_line, span.start_column), (1, 5));
+        assert_eq!((span.end_line, span.end_column), (2, 4));
+    }
+
+    #[test]
+    fn test_span_offsets_are_bytes() {
+        let scanner = scan("\"é\" x");
+
+        assert_eq!(scanner.tokens[0].span().len(), 4);
+        assert_eq!(scanner.tokens[1].span().start, 5);
+        assert_eq!(scanner.tokens[1].column(), 5);
+    }
+
+    #[test]
+    fn test_lexemes_borrow_from_source() {
+        let source = "var name = \"lox\";";
+        let scanner = scan(source);
+
+        let range = source.as_bytes().as_ptr_range();
+        assert!(scanner.tokens.iter().all(|token| range.contains(&token.lexeme.as_ptr()) || token.lexeme.is_empty()));
+        assert_eq!(scanner.tokens[1].lexeme, "name");
+    }
+
+    #[test]
+    fn test_non_ascii_outside_strings() {
+        let scanner = scan("a\u{a0}b → c");
+
+        assert_eq!(scanner.tokens.len(), 4);
+        assert_eq!(scanner.diagnostics.len(), 1);
+        assert_eq!(scanner.diagnostics[0].message, "Unexpected character '→' (U+2192).");
+        assert_eq!(scanner.diagnostics[0].primary.span.len(), 3);
+        assert_eq!(scanner.diagnostics[0].primary.span.start_column, 5);
+        assert_eq!(scanner.tokens[2].column(), 7);
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let scanner = scan("var 이름 = café_2 + ñ;");
+
+        assert!(scanner.diagnostics.is_empty());
+        assert_eq!(scanner.tokens[1].token_type, TokenType::Identifier);
+        assert_eq!(scanner.tokens[1].lexeme, "이름");
+        assert_eq!(scanner.tokens[3].lexeme, "café_2");
+        assert_eq!(scanner.tokens[3].column(), 10);
+        assert_eq!(scanner.tokens[5].lexeme, "ñ");
+        assert_eq!(scanner.tokens[5].column(), 19);
+    }
+
+    #[test]
+    fn test_identifiers_are_normalized() {
+        let composed = scan("caf\u{e9}");
+        let decomposed = scan("cafe\u{301}");
+
+        assert!(decomposed.diagnostics.is_empty());
+        assert_eq!(decomposed.tokens[0].lexeme, "cafe\u{301}");
+        assert_eq!(decomposed.tokens[0].symbol(), composed.tokens[0].symbol());
+        assert_eq!(decomposed.tokens[0].column(), 1);
+    }
+
+    #[test]
+    fn test_unexpected_character_names_code_point() {
+        let scanner = scan("a \u{301} \u{7}");
+
+        let messages: Vec<_> = scanner.diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, vec!["Unexpected character '\\u{301}' (U+0301).", "Unexpected character '\\u{7}' (U+0007)."]);
+    }
+
+    #[test]
+    fn test_iterator_yields_eof_once() {
+        let mut collector = Collector::new();
+        let mut scanner = Scanner::new("a", &mut collector);
+
+        assert_eq!(scanner.next().map(|token| token.token_type), Some(TokenType::Identifier));
+        assert_eq!(scanner.next().map(|token| token.token_type), Some(TokenType::Eof));
+        assert!(scanner.next().is_none());
+        assert!(scanner.next().is_none());
+    }
+
+    #[test]
+    fn test_iterator_scans_on_demand() {
+        let mut collector = Collector::new();
+        let mut scanner = Scanner::new("a @ b", &mut collector);
+
+        scanner.next();
+        assert!(!scanner.had_error());
+        assert_eq!(scanner.next().map(|token| token.lexeme), Some("b"));
+        assert!(scanner.had_error());
+    }
+
+    #[test]
+    fn test_token_stream_stays_at_eof() {
+        let mut collector = Collector::new();
+        let mut tokens = TokenStream::new(Scanner::new("a;", &mut collector));
+
+        assert_eq!(tokens.peek().lexeme, "a");
+        tokens.advance();
+        assert_eq!(tokens.advance().token_type, TokenType::Semicolon);
+        assert_eq!(tokens.peek().token_type, TokenType::Eof);
+        tokens.advance();
+        assert_eq!(tokens.previous().token_type, TokenType::Semicolon);
+        assert_eq!(tokens.peek().token_type, TokenType::Eof);
+    }
+}
@@ -0,0 +1,64 @@
+/// A region of source text. Offsets are in bytes, lines and columns are
+/// 1-based, and both `end` and `end_column` point just past the last character.
+///
+/// Lines and columns are `u32` to keep tokens, and the errors that carry
+/// them, small.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+impl Span {
+    /// A zero-width span on `line`, for nodes that have no source text of their own.
+    pub fn line(line: usize) -> Self {
+        Span {
+            start_line: line as u32,
+            end_line: line as u32,
+            ..Span::default()
+        }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(&self, other: Span) -> Span {
+        let (first, last) = if self.start <= other.start { (*self, other) } else { (other, *self) };
+        let end = if first.end >= last.end { first } else { last };
+
+        Span {
+            start: first.start,
+            start_line: first.start_line,
+            start_column: first.start_column,
+            end: end.end,
+            end_line: end.end_line,
+            end_column: end.end_column,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_covers_both_spans() {
+        let lhs = Span { start: 0, end: 1, start_line: 1, start_column: 1, end_line: 1, end_column: 2 };
+        let rhs = Span { start: 4, end: 9, start_line: 2, start_column: 1, end_line: 2, end_column: 6 };
+
+        let expected = Span { start: 0, end: 9, start_line: 1, start_column: 1, end_line: 2, end_column: 6 };
+        assert_eq!(lhs.to(rhs), expected);
+        assert_eq!(rhs.to(lhs), expected);
+        assert_eq!(lhs.to(rhs).len(), 9);
+    }
+}
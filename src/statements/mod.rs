@@ -0,0 +1,7 @@
+use crate::statements::stmt::Stmt;
+
+pub mod stmt;
+
+pub trait Visitor<'src, T> {
+    fn visit_stmt(&self, stmt: &Stmt<'src>) -> T;
+}
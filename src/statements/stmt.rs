@@ -0,0 +1,36 @@
+use std::rc::Rc;
+
+use crate::expressions::expr::Expr;
+use crate::statements::Visitor;
+use crate::token::Token;
+
+#[derive(Debug, PartialEq)]
+pub enum Stmt<'src> {
+    Block(Vec<Stmt<'src>>),
+    Class(Token<'src>, Option<Expr<'src>>, Vec<Rc<Function<'src>>>),
+    Expression(Expr<'src>),
+    Function(Rc<Function<'src>>),
+    If(Expr<'src>, Box<Stmt<'src>>, Option<Box<Stmt<'src>>>),
+    Print(Expr<'src>),
+    Return(Token<'src>, Option<Expr<'src>>),
+    Var(Token<'src>, Option<Expr<'src>>),
+    While(Expr<'src>, Box<Stmt<'src>>),
+}
+
+/// A function declaration. It is reference counted because every function value
+/// created from it keeps the declaration alive after the surrounding tree is gone.
+#[derive(Debug, PartialEq)]
+pub struct Function<'src> {
+    pub name: Token<'src>,
+    pub params: Vec<Token<'src>>,
+    pub body: Vec<Stmt<'src>>,
+}
+
+impl<'src> Stmt<'src> {
+    pub fn accept<V, T>(&self, visitor: &V) -> T
+    where
+        V: Visitor<'src, T>,
+    {
+        visitor.visit_stmt(self)
+    }
+}
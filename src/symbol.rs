@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+/// An interned string: identifiers and string literals are stored once, and
+/// everything else refers to them by index. Comparing or hashing a symbol is
+/// comparing or hashing a `u32`.
+///
+/// Interned text is never freed, so it lives as long as the process does.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+// Words every program uses, interned up front so they have fixed symbols.
+// Each one must stay at the index of its constant below.
+const PREDEFINED: [&str; 17] = [
+    "and", "class", "else", "false", "for", "fun", "if", "nil", "or", "print", "return", "super", "this", "true",
+    "var", "while", "init",
+];
+
+impl Symbol {
+    pub const AND: Symbol = Symbol(0);
+    pub const CLASS: Symbol = Symbol(1);
+    pub const ELSE: Symbol = Symbol(2);
+    pub const FALSE: Symbol = Symbol(3);
+    pub const FOR: Symbol = Symbol(4);
+    pub const FUN: Symbol = Symbol(5);
+    pub const IF: Symbol = Symbol(6);
+    pub const NIL: Symbol = Symbol(7);
+    pub const OR: Symbol = Symbol(8);
+    pub const PRINT: Symbol = Symbol(9);
+    pub const RETURN: Symbol = Symbol(10);
+    pub const SUPER: Symbol = Symbol(11);
+    pub const THIS: Symbol = Symbol(12);
+    pub const TRUE: Symbol = Symbol(13);
+    pub const VAR: Symbol = Symbol(14);
+    pub const WHILE: Symbol = Symbol(15);
+    pub const INIT: Symbol = Symbol(16);
+
+    /// The symbol for `text`, interning it if it hasn't been seen before.
+    pub fn intern(text: &str) -> Symbol {
+        interner().intern(text)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        interner().strings[self.0 as usize]
+    }
+
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Symbol({:?})", self.as_str())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+impl Interner {
+    fn new() -> Self {
+        let mut interner = Interner {
+            symbols: HashMap::new(),
+            strings: vec![],
+        };
+        for text in PREDEFINED {
+            interner.intern(text);
+        }
+        interner
+    }
+
+    fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(text) {
+            return symbol;
+        }
+
+        // Leaking gives the text a 'static lifetime, so `as_str` can hand it
+        // out without holding the lock.
+        let text: &'static str = Box::leak(text.to_string().into_boxed_str());
+        let symbol = Symbol(u32::try_from(self.strings.len()).expect("too many symbols"));
+        self.strings.push(text);
+        self.symbols.insert(text, symbol);
+        symbol
+    }
+}
+
+static INTERNER: LazyLock<Mutex<Interner>> = LazyLock::new(|| Mutex::new(Interner::new()));
+
+fn interner() -> MutexGuard<'static, Interner> {
+    // The interner is never left half-updated, so a poisoned lock is still usable.
+    INTERNER.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_is_idempotent() {
+        let a = Symbol::intern("symbol_test_a");
+        let b = Symbol::intern("symbol_test_b");
+
+        assert_eq!(Symbol::intern("symbol_test_a"), a);
+        assert_ne!(a, b);
+        assert_eq!(a.as_str(), "symbol_test_a");
+        assert_eq!(b.to_string(), "symbol_test_b");
+    }
+
+    #[test]
+    fn test_predefined_symbols() {
+        for (index, text) in PREDEFINED.iter().enumerate() {
+            assert_eq!(Symbol::intern(text).as_u32() as usize, index);
+        }
+        assert_eq!(Symbol::INIT.as_str(), "init");
+        assert_eq!(Symbol::WHILE.as_str(), "while");
+    }
+}
@@ -1,5 +1,8 @@
 use std::fmt;
 
+use crate::span::Span;
+use crate::symbol::Symbol;
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum TokenType {
     // Single-character tokens.
@@ -29,6 +32,10 @@ pub enum TokenType {
     Identifier,
     String,
     Number,
+    // The parts of an interpolated string: each part up to a `${`, then the
+    // last part from the final `}` to the closing quote.
+    Interpolation,
+    InterpolationEnd,
 
     // Keywords.
     And,
@@ -51,33 +58,48 @@ pub enum TokenType {
     Eof,
 }
 
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = format!("{:?}", self);
+        let mut upper = String::new();
+        for (i, c) in name.chars().enumerate() {
+            if i > 0 && c.is_uppercase() {
+                upper.push('_');
+            }
+            upper.push(c.to_ascii_uppercase());
+        }
+        write!(f, "{}", upper)
+    }
+}
+
 impl TokenType {
-    pub fn keyword(text: &str) -> Option<Self> {
-        match text {
-            "and" => Some(TokenType::And),
-            "class" => Some(TokenType::Class),
-            "else" => Some(TokenType::Else),
-            "false" => Some(TokenType::False),
-            "for" => Some(TokenType::For),
-            "fun" => Some(TokenType::Fun),
-            "if" => Some(TokenType::If),
-            "nil" => Some(TokenType::Nil),
-            "or" => Some(TokenType::Or),
-            "print" => Some(TokenType::Print),
-            "return" => Some(TokenType::Return),
-            "super" => Some(TokenType::Super),
-            "this" => Some(TokenType::This),
-            "true" => Some(TokenType::True),
-            "var" => Some(TokenType::Var),
-            "while" => Some(TokenType::While),
+    /// The keyword spelled by an identifier-like word, if it is one.
+    pub fn keyword(word: Symbol) -> Option<Self> {
+        match word {
+            Symbol::AND => Some(TokenType::And),
+            Symbol::CLASS => Some(TokenType::Class),
+            Symbol::ELSE => Some(TokenType::Else),
+            Symbol::FALSE => Some(TokenType::False),
+            Symbol::FOR => Some(TokenType::For),
+            Symbol::FUN => Some(TokenType::Fun),
+            Symbol::IF => Some(TokenType::If),
+            Symbol::NIL => Some(TokenType::Nil),
+            Symbol::OR => Some(TokenType::Or),
+            Symbol::PRINT => Some(TokenType::Print),
+            Symbol::RETURN => Some(TokenType::Return),
+            Symbol::SUPER => Some(TokenType::Super),
+            Symbol::THIS => Some(TokenType::This),
+            Symbol::TRUE => Some(TokenType::True),
+            Symbol::VAR => Some(TokenType::Var),
+            Symbol::WHILE => Some(TokenType::While),
             _ => None,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum TokenLiteral {
-    String(String),
+    String(Symbol),
     Number(f64),
     True,
     False,
@@ -96,55 +118,100 @@ impl fmt::Display for TokenLiteral {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Token {
+/// A token, whose lexeme borrows from the source it was scanned from.
+#[derive(Debug, Clone)]
+pub struct Token<'src> {
     pub token_type: TokenType,
-    pub lexeme: String,
+    pub lexeme: &'src str,
     pub literal: Option<TokenLiteral>,
-    line: usize,
+    // The interned lexeme of identifiers and keywords.
+    symbol: Option<Symbol>,
+    span: Span,
+}
+
+// The symbol only caches the lexeme, so it takes no part in equality.
+impl PartialEq for Token<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type
+            && self.lexeme == other.lexeme
+            && self.literal == other.literal
+            && self.span == other.span
+    }
 }
 
-impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: Option<TokenLiteral>, line: usize) -> Self {
+impl<'src> Token<'src> {
+    pub fn new(token_type: TokenType, lexeme: &'src str, literal: Option<TokenLiteral>, span: Span) -> Self {
         Token {
             token_type,
             lexeme,
             literal,
-            line,
+            symbol: None,
+            span,
         }
     }
 
-    pub fn eof(line: usize) -> Self {
+    /// A token for a word the scanner has already interned as `symbol`.
+    pub fn word(token_type: TokenType, lexeme: &'src str, symbol: Symbol, span: Span) -> Self {
+        Token {
+            symbol: Some(symbol),
+            ..Token::new(token_type, lexeme, None, span)
+        }
+    }
+
+    /// The lexeme as a symbol, for tokens that name something.
+    pub fn symbol(&self) -> Symbol {
+        self.symbol.unwrap_or_else(|| Symbol::intern(self.lexeme))
+    }
+
+    /// The line the token starts on.
+    pub fn line(&self) -> usize {
+        self.span.start_line as usize
+    }
+
+    pub fn column(&self) -> usize {
+        self.span.start_column as usize
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn eof(span: Span) -> Self {
         Token {
             token_type: TokenType::Eof,
-            lexeme: String::from(""),
+            lexeme: "",
             literal: None,
-            line,
+            symbol: None,
+            span,
         }
     }
 
+    #[cfg(test)]
     pub fn minus(line: usize) -> Self {
         Token {
             token_type: TokenType::Minus,
-            lexeme: String::from("-"),
+            lexeme: "-",
             literal: None,
-            line,
+            symbol: None,
+            span: Span::line(line),
         }
     }
 
+    #[cfg(test)]
     pub fn star(line: usize) -> Self {
         Token {
             token_type: TokenType::Star,
-            lexeme: String::from("*"),
+            lexeme: "*",
             literal: None,
-            line,
+            symbol: None,
+            span: Span::line(line),
         }
     }
 }
 
-impl fmt::Display for Token {
+impl fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?} {} {}", self.token_type, self.lexeme, self.line)
+        write!(f, "{} {} {}", self.token_type, self.lexeme, self.line())
     }
 }
 
@@ -156,11 +223,26 @@ mod tests {
     fn token_to_string() {
         let token = Token {
             token_type: TokenType::Class,
-            lexeme: String::from("class"),
+            lexeme: "class",
             literal: None,
-            line: 10,
+            symbol: None,
+            span: Span::line(10),
         };
 
         assert_eq!(token.to_string(), String::from("CLASS class 10"));
     }
+
+    #[test]
+    fn token_type_to_string() {
+        assert_eq!(TokenType::LeftParen.to_string(), "LEFT_PAREN");
+        assert_eq!(TokenType::Eof.to_string(), "EOF");
+    }
+
+    #[test]
+    fn test_keywords_by_symbol() {
+        assert_eq!(TokenType::keyword(Symbol::intern("while")), Some(TokenType::While));
+        assert_eq!(TokenType::keyword(Symbol::intern("this")), Some(TokenType::This));
+        assert_eq!(TokenType::keyword(Symbol::intern("whilst")), None);
+        assert_eq!(TokenType::keyword(Symbol::INIT), None);
+    }
 }
\ No newline at end of file
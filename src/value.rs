@@ -0,0 +1,97 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::callable::{LoxFunction, NativeFunction};
+use crate::class::{LoxClass, LoxInstance};
+use crate::token::TokenLiteral;
+
+#[derive(Debug, Clone)]
+pub enum Value<'src> {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    // Only literals in the source are interned. Strings built while running,
+    // like the result of `+`, are owned by the values holding them, and are
+    // compared by their contents.
+    String(Rc<str>),
+    Function(Rc<LoxFunction<'src>>),
+    NativeFunction(Rc<NativeFunction<'src>>),
+    Class(Rc<LoxClass<'src>>),
+    Instance(Rc<RefCell<LoxInstance<'src>>>),
+}
+
+impl Value<'_> {
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Nil => false,
+            Value::Bool(b) => *b,
+            _ => true,
+        }
+    }
+}
+
+impl PartialEq for Value<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Function(l), Value::Function(r)) => Rc::ptr_eq(l, r),
+            (Value::NativeFunction(l), Value::NativeFunction(r)) => Rc::ptr_eq(l, r),
+            (Value::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
+            (Value::Instance(l), Value::Instance(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl From<&TokenLiteral> for Value<'_> {
+    fn from(literal: &TokenLiteral) -> Self {
+        match literal {
+            TokenLiteral::String(s) => Value::String(Rc::from(s.as_str())),
+            TokenLiteral::Number(n) => Value::Number(*n),
+            TokenLiteral::True => Value::Bool(true),
+            TokenLiteral::False => Value::Bool(false),
+            TokenLiteral::Nil => Value::Nil,
+        }
+    }
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Function(function) => write!(f, "{}", function),
+            Value::NativeFunction(function) => write!(f, "{}", function),
+            Value::Class(class) => write!(f, "{}", class),
+            Value::Instance(instance) => write!(f, "{}", instance.borrow()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truthiness() {
+        assert!(!Value::Nil.is_truthy());
+        assert!(!Value::Bool(false).is_truthy());
+        assert!(Value::Bool(true).is_truthy());
+        assert!(Value::Number(0.0).is_truthy());
+        assert!(Value::String(Rc::from("")).is_truthy());
+    }
+
+    #[test]
+    fn test_value_to_string() {
+        assert_eq!(Value::Number(7.0).to_string(), "7");
+        assert_eq!(Value::Number(2.5).to_string(), "2.5");
+        assert_eq!(Value::Nil.to_string(), "nil");
+        assert_eq!(Value::String(Rc::from("lox")).to_string(), "lox");
+    }
+}
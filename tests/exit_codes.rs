@@ -0,0 +1,84 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Writes `source` to a script of its own in the temp directory.
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("lox-exit-codes-{}-{name}.lox", std::process::id()));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+/// Runs the `lox` binary and returns its exit code.
+fn lox(args: &[&str]) -> i32 {
+    let output = Command::new(env!("CARGO_BIN_EXE_lox")).args(args).output().unwrap();
+    output.status.code().expect("lox was killed by a signal")
+}
+
+fn run(backend: &str, path: &Path) -> i32 {
+    lox(&[&format!("--backend={backend}"), "run", path.to_str().unwrap()])
+}
+
+/// Runs the `lox` binary with a reader that closes stdout without reading it.
+fn lox_into_closed_pipe(args: &[&str]) -> i32 {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    child.wait().unwrap().code().expect("lox was killed by a signal")
+}
+
+#[test]
+fn test_success() {
+    let path = script("success", "print 1 + 2;");
+    assert_eq!(run("tree", &path), 0);
+    assert_eq!(run("vm", &path), 0);
+}
+
+#[test]
+fn test_usage() {
+    assert_eq!(lox(&["run"]), 64);
+    assert_eq!(lox(&["--no-such-option"]), 64);
+    assert_eq!(lox(&["--backend=nope", "run", "a.lox"]), 64);
+    assert_eq!(lox(&["frobnicate", "a.lox"]), 64);
+}
+
+#[test]
+fn test_compile_error() {
+    let path = script("compile-error", "print (1 + ;");
+    assert_eq!(run("tree", &path), 65);
+    assert_eq!(run("vm", &path), 65);
+    assert_eq!(lox(&["check", path.to_str().unwrap()]), 65);
+}
+
+#[test]
+fn test_unfinished_interpolation() {
+    let path = script("unfinished-interpolation", "print \"${\";");
+    assert_eq!(run("tree", &path), 65);
+    assert_eq!(run("vm", &path), 65);
+}
+
+#[test]
+fn test_missing_file() {
+    assert_eq!(lox(&["run", "no/such/script.lox"]), 66);
+}
+
+#[test]
+fn test_runtime_error() {
+    let path = script("runtime-error", "print -nil;");
+    assert_eq!(run("tree", &path), 70);
+    assert_eq!(run("vm", &path), 70);
+}
+
+#[test]
+fn test_broken_pipe() {
+    // Far more output than a pipe buffers, so lox is still writing when the
+    // reader goes away.
+    let path = script("broken-pipe", &"print 1;\n".repeat(50_000));
+    assert_eq!(lox_into_closed_pipe(&["tokens", path.to_str().unwrap()]), 0);
+    assert_eq!(lox_into_closed_pipe(&["ast", path.to_str().unwrap()]), 0);
+}